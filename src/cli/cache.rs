@@ -0,0 +1,173 @@
+use anyhow::{format_err, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use stylua_lib::Config;
+use tempfile::NamedTempFile;
+
+use crate::normalize_path;
+
+/// The first line of a cache file. Caches written by a different version of StyLua are discarded,
+/// as the formatting of a file may have changed between versions
+const CACHE_HEADER: &str = concat!("stylua-cache ", env!("CARGO_PKG_VERSION"));
+
+/// Hashes the given bytes using 64-bit FNV-1a, which gives the same hash on every platform and every run
+pub fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Hashes the configuration used to format a file, so that changing any option invalidates its cache entry
+pub fn hash_config(config: &Config) -> u64 {
+    hash(format!("{:?}", config).as_bytes())
+}
+
+/// The default location of the cache for the given working directory.
+/// This is under `$XDG_CACHE_HOME` if it is set, otherwise it is a `.stylua-cache` file in the working directory
+pub fn default_location(cwd: &Path) -> PathBuf {
+    match std::env::var_os("XDG_CACHE_HOME") {
+        Some(cache_home) if !cache_home.is_empty() => PathBuf::from(cache_home)
+            .join("stylua")
+            .join(format!("{:016x}", hash(cwd.to_string_lossy().as_bytes()))),
+        _ => cwd.join(".stylua-cache"),
+    }
+}
+
+/// A file which is known to be formatted
+#[derive(Clone, Copy, Debug)]
+struct CacheEntry {
+    config_hash: u64,
+    content_hash: u64,
+}
+
+impl CacheEntry {
+    fn parse(line: &str) -> Option<(PathBuf, Self)> {
+        // The path is last, so that it may contain tabs
+        let mut parts = line.splitn(3, '\t');
+        let config_hash = u64::from_str_radix(parts.next()?, 16).ok()?;
+        let content_hash = u64::from_str_radix(parts.next()?, 16).ok()?;
+        let path = PathBuf::from(parts.next()?);
+
+        Some((
+            path,
+            Self {
+                config_hash,
+                content_hash,
+            },
+        ))
+    }
+
+    fn to_line(self, path: &Path) -> String {
+        format!(
+            "{:016x}\t{:016x}\t{}",
+            self.config_hash,
+            self.content_hash,
+            path.display()
+        )
+    }
+}
+
+/// An on-disk record of the files which are already formatted, keyed by their absolute path.
+/// A file is skipped whilst its contents and the configuration used to format it are the same as when it was recorded
+pub struct Cache {
+    location: PathBuf,
+    /// The directory which relative paths are resolved from
+    root: PathBuf,
+    entries: Mutex<HashMap<PathBuf, CacheEntry>>,
+}
+
+impl Cache {
+    /// Loads the cache from the given location.
+    /// A missing, unreadable or outdated cache is treated as empty, and replaced when the cache is saved
+    pub fn load(location: PathBuf, root: PathBuf) -> Self {
+        let mut entries = HashMap::new();
+
+        if let Ok(contents) = fs::read_to_string(&location) {
+            let mut lines = contents.lines();
+            if lines.next() == Some(CACHE_HEADER) {
+                entries.extend(lines.filter_map(CacheEntry::parse));
+            }
+        }
+
+        Self {
+            location,
+            root,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    /// The absolute path of the given file, used as its key in the cache
+    fn key(&self, path: &Path) -> PathBuf {
+        normalize_path(&self.root.join(path))
+    }
+
+    fn entries(&self) -> MutexGuard<'_, HashMap<PathBuf, CacheEntry>> {
+        self.entries.lock().expect("cache poisoned")
+    }
+
+    /// Returns whether the given contents of the file are known to be formatted
+    pub fn is_formatted(&self, path: &Path, config_hash: u64, contents: &str) -> bool {
+        match self.entries().get(&self.key(path)) {
+            Some(entry) => {
+                entry.config_hash == config_hash && entry.content_hash == hash(contents.as_bytes())
+            }
+            None => false,
+        }
+    }
+
+    /// Records that the file is formatted, with the given contents
+    pub fn insert(&self, path: &Path, config_hash: u64, contents: &str) {
+        let entry = CacheEntry {
+            config_hash,
+            content_hash: hash(contents.as_bytes()),
+        };
+        let key = self.key(path);
+        self.entries().insert(key, entry);
+    }
+
+    /// Forgets the file, as it is no longer known to be formatted
+    pub fn remove(&self, path: &Path) {
+        let key = self.key(path);
+        self.entries().remove(&key);
+    }
+
+    /// Writes the cache back to disk, dropping any files which no longer exist.
+    /// The cache is written to a uniquely named temporary file first, so that instances of StyLua running at the same time
+    /// never read a partial cache, or write over each other's temporary files
+    pub fn save(&self) -> Result<()> {
+        let mut lines: Vec<_> = self
+            .entries()
+            .iter()
+            .filter(|(path, _)| path.is_file() && !path.to_string_lossy().contains('\n'))
+            .map(|(path, entry)| entry.to_line(path))
+            .collect();
+        lines.sort();
+
+        let mut contents = String::from(CACHE_HEADER);
+        for line in lines {
+            contents.push('\n');
+            contents.push_str(&line);
+        }
+        contents.push('\n');
+
+        let write = || -> std::io::Result<()> {
+            let directory = self.location.parent().unwrap_or(&self.root);
+            fs::create_dir_all(directory)?;
+            let mut temporary_file = NamedTempFile::new_in(directory)?;
+            temporary_file.write_all(contents.as_bytes())?;
+            temporary_file.persist(&self.location)?;
+            Ok(())
+        };
+
+        write().map_err(|error| {
+            format_err!(
+                "error: could not write cache to {}: {}",
+                self.location.display(),
+                error
+            )
+        })
+    }
+}
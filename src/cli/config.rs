@@ -1,76 +1,381 @@
 use anyhow::{bail, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
-use stylua_lib::{Config, IndentType, LineEndings};
-
-#[derive(Debug, Deserialize)]
-#[serde(remote = "LineEndings")]
-enum LineEndingsDef {
-    #[serde(rename = "lf")]
-    Unix,
-    #[serde(rename = "crlf")]
-    Windows,
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use stylua_lib::{
+    CallParenType, CommentStyle, Config, IndentType, KeyStyle, LineEndings, NumberCase, QuoteStyle,
+    SemicolonStyle,
+};
+
+use crate::editorconfig;
+use crate::normalize_path;
+
+/// The options which can be set in an `[[overrides]]` section of a configuration file, or an `.editorconfig` section.
+/// Any options which are set replace those in the base configuration.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartialConfig {
+    pub column_width: Option<usize>,
+    pub line_endings: Option<LineEndings>,
+    pub indent_type: Option<IndentType>,
+    pub indent_width: Option<usize>,
+    pub quote_style: Option<QuoteStyle>,
+    pub call_parentheses: Option<CallParenType>,
+    pub collapse_simple_statement: Option<bool>,
+    pub max_blank_lines: Option<usize>,
+    pub max_top_level_blank_lines: Option<usize>,
+    pub hex_prefix_case: Option<NumberCase>,
+    pub hex_digit_case: Option<NumberCase>,
+    pub exponent_case: Option<NumberCase>,
+    pub remove_exponent_plus: Option<bool>,
+    pub normalize_trailing_dot: Option<bool>,
+    pub format_multiline_comments: Option<bool>,
+    pub comment_style: Option<CommentStyle>,
+    pub align_assignments: Option<bool>,
+    pub align_assignments_max_padding: Option<usize>,
+    pub key_style: Option<KeyStyle>,
+    pub semicolons: Option<SemicolonStyle>,
+    pub verify: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(remote = "IndentType")]
-enum IndentTypeDef {
-    #[serde(rename = "tab")]
-    Tabs,
-    #[serde(rename = "space")]
-    Spaces,
+impl PartialConfig {
+    fn apply(&self, mut config: Config) -> Config {
+        if let Some(column_width) = self.column_width {
+            config = config.with_column_width(column_width);
+        }
+        if let Some(line_endings) = self.line_endings {
+            config = config.with_line_endings(line_endings);
+        }
+        if let Some(indent_type) = self.indent_type {
+            config = config.with_indent_type(indent_type);
+        }
+        if let Some(indent_width) = self.indent_width {
+            config = config.with_indent_width(indent_width);
+        }
+        if let Some(quote_style) = self.quote_style {
+            config = config.with_quote_style(quote_style);
+        }
+        if let Some(call_parentheses) = self.call_parentheses {
+            config = config.with_call_parentheses(call_parentheses);
+        }
+        if let Some(collapse_simple_statement) = self.collapse_simple_statement {
+            config = config.with_collapse_simple_statement(collapse_simple_statement);
+        }
+        if let Some(max_blank_lines) = self.max_blank_lines {
+            config = config.with_max_blank_lines(max_blank_lines);
+        }
+        if let Some(max_top_level_blank_lines) = self.max_top_level_blank_lines {
+            config = config.with_max_top_level_blank_lines(Some(max_top_level_blank_lines));
+        }
+        if let Some(hex_prefix_case) = self.hex_prefix_case {
+            config = config.with_hex_prefix_case(hex_prefix_case);
+        }
+        if let Some(hex_digit_case) = self.hex_digit_case {
+            config = config.with_hex_digit_case(hex_digit_case);
+        }
+        if let Some(exponent_case) = self.exponent_case {
+            config = config.with_exponent_case(exponent_case);
+        }
+        if let Some(remove_exponent_plus) = self.remove_exponent_plus {
+            config = config.with_remove_exponent_plus(remove_exponent_plus);
+        }
+        if let Some(normalize_trailing_dot) = self.normalize_trailing_dot {
+            config = config.with_normalize_trailing_dot(normalize_trailing_dot);
+        }
+        if let Some(format_multiline_comments) = self.format_multiline_comments {
+            config = config.with_format_multiline_comments(format_multiline_comments);
+        }
+        if let Some(comment_style) = self.comment_style {
+            config = config.with_comment_style(comment_style);
+        }
+        if let Some(align_assignments) = self.align_assignments {
+            config = config.with_align_assignments(align_assignments);
+        }
+        if let Some(align_assignments_max_padding) = self.align_assignments_max_padding {
+            config = config.with_align_assignments_max_padding(align_assignments_max_padding);
+        }
+        if let Some(key_style) = self.key_style {
+            config = config.with_key_style(key_style);
+        }
+        if let Some(semicolons) = self.semicolons {
+            config = config.with_semicolons(semicolons);
+        }
+        if let Some(verify) = self.verify {
+            config = config.with_verify(verify);
+        }
+        config
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct ConfigDef {
-    #[serde(rename = "end_of_line", with = "LineEndingsDef", default)]
-    line_endings: LineEndings,
-    #[serde(rename = "indent_style", with = "IndentTypeDef", default)]
-    indent_type: IndentType,
-    #[serde(rename = "indent_size", default)]
-    indent_width: usize,
+/// A partial configuration which applies to any files matching its globs.
+/// The globs are matched against paths relative to the directory of the file the override was read from.
+#[derive(Clone, Debug)]
+struct Override {
+    directory: PathBuf,
+    files: GlobSet,
+    config: PartialConfig,
 }
 
-#[derive(Debug, Deserialize)]
-struct Section {
-    #[serde(rename = "*", alias = "*.lua")]
-    lua: ConfigDef,
+impl Override {
+    fn is_match(&self, path: &Path) -> bool {
+        match path.strip_prefix(&self.directory) {
+            Ok(relative_path) => self.files.is_match(relative_path),
+            Err(_) => false,
+        }
+    }
 }
 
-pub fn from_toml(content: &str) -> Result<Config> {
-    match toml::from_str(&content) {
-        Ok(config) => Ok(config),
-        Err(error) => bail!("error: config file not in correct format: {}", error),
+/// A configuration, made up of the base configuration and any overrides for specific files
+#[derive(Debug)]
+pub struct ConfigFile {
+    config: Config,
+    overrides: Vec<Override>,
+}
+
+impl ConfigFile {
+    /// Returns the configuration for the file at the given absolute path, with any matching overrides applied in order
+    fn config_for(&self, path: &Path) -> Config {
+        self.overrides
+            .iter()
+            .filter(|config_override| config_override.is_match(path))
+            .fold(self.config, |config, config_override| {
+                config_override.config.apply(config)
+            })
     }
 }
 
-pub fn from_ini(content: &str) -> Result<Config> {
-    let section: Section = match serde_ini::from_str(content) {
-        Ok(section) => section,
+/// Builds a set of globs where `*` does not match across directories, so `**` must be used instead
+fn build_glob_set<'a>(patterns: impl IntoIterator<Item = &'a str>) -> Result<GlobSet> {
+    let mut glob_set = GlobSetBuilder::new();
+    for pattern in patterns {
+        match GlobBuilder::new(pattern).literal_separator(true).build() {
+            Ok(glob) => glob_set.add(glob),
+            Err(error) => bail!("error: cannot parse glob pattern {}: {}", pattern, error),
+        };
+    }
+
+    Ok(glob_set.build()?)
+}
+
+fn parse_override(value: toml::Value, directory: &Path) -> Result<Override> {
+    let mut table = match value {
+        toml::Value::Table(table) => table,
+        _ => bail!("error: config file not in correct format: overrides must be tables"),
+    };
+
+    let files: Vec<String> = match table.remove("files").map(toml::Value::try_into) {
+        Some(Ok(files)) => files,
+        Some(Err(error)) => bail!("error: config file not in correct format: {}", error),
+        None => bail!("error: config file not in correct format: overrides must contain `files`"),
+    };
+
+    let config = match toml::Value::Table(table).try_into() {
+        Ok(config) => config,
+        Err(error) => bail!("error: config file not in correct format: {}", error),
+    };
+
+    Ok(Override {
+        directory: directory.to_owned(),
+        files: build_glob_set(files.iter().map(String::as_str))?,
+        config,
+    })
+}
+
+/// Parses the contents of a stylua.toml file found in the given directory
+pub fn from_toml(content: &str, directory: &Path) -> Result<ConfigFile> {
+    let mut table: toml::value::Table = match toml::from_str(content) {
+        Ok(table) => table,
+        Err(error) => bail!("error: config file not in correct format: {}", error),
+    };
+
+    // Overrides are separated out so that the rest can be parsed as a strict `Config`
+    let overrides = match table.remove("overrides") {
+        Some(toml::Value::Array(overrides)) => overrides
+            .into_iter()
+            .map(|value| parse_override(value, directory))
+            .collect::<Result<_>>()?,
+        Some(_) => {
+            bail!("error: config file not in correct format: overrides must be an array of tables")
+        }
+        None => Vec::new(),
+    };
+
+    let config = match toml::Value::Table(table).try_into() {
+        Ok(config) => config,
         Err(error) => bail!("error: config file not in correct format: {}", error),
     };
 
-    let config = section.lua;
-    Ok(Config::new()
-        .with_line_endings(config.line_endings)
-        .with_indent_type(config.indent_type)
-        .with_indent_width(config.indent_width))
+    Ok(ConfigFile { config, overrides })
 }
 
-pub fn read() -> Result<Config> {
-    match fs::read_to_string("stylua.toml") {
-        Ok(content) => from_toml(&content),
-        Err(_) => match fs::read_to_string(".editorconfig") {
-            Ok(content) => from_ini(&content),
-            Err(_) => Ok(Config::default()),
-        },
+/// The names of configuration files to search for, in order of preference
+const CONFIG_FILE_NAMES: [&str; 2] = ["stylua.toml", ".stylua.toml"];
+
+/// Reads the `.editorconfig` file in the given directory, if there is one
+fn read_editorconfig(directory: &Path) -> Result<Option<(bool, Vec<Override>)>> {
+    let path = directory.join(".editorconfig");
+    if !path.is_file() {
+        return Ok(None);
     }
+
+    let editorconfig = match fs::read_to_string(&path) {
+        Ok(content) => editorconfig::parse(&content),
+        Err(error) => bail!("error: couldn't read config file: {}", error),
+    };
+
+    // A section with a glob which cannot be parsed is skipped, rather than stopping StyLua from running,
+    // as the file may have been written for other tools
+    let overrides = editorconfig
+        .sections
+        .into_iter()
+        .filter_map(|section| {
+            let files = GlobBuilder::new(&section.glob)
+                .literal_separator(true)
+                .build()
+                .and_then(|glob| GlobSetBuilder::new().add(glob).build());
+            match files {
+                Ok(files) => Some(Override {
+                    directory: directory.to_owned(),
+                    files,
+                    config: section.config,
+                }),
+                Err(error) => {
+                    eprintln!("warning: ignoring section in {}: {}", path.display(), error);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    Ok(Some((editorconfig.root, overrides)))
 }
 
-pub fn read_from_path(path: &Path) -> Result<Config> {
+/// Reads the configuration file at the given absolute path
+pub fn read_from_path(path: &Path) -> Result<ConfigFile> {
+    let directory = path.parent().unwrap_or(path);
     match fs::read_to_string(path) {
-        Ok(content) => from_toml(&content),
+        Ok(content) => from_toml(&content, directory),
         Err(error) => bail!("error: couldn't read config file: {}", error),
     }
 }
+
+/// Code read from stdin is treated as a file with this name in the current working directory,
+/// so that any globs such as `*.lua` still apply to it
+const STDIN_FILE_NAME: &str = "stdin.lua";
+
+/// Finds the configuration to use for each file, from the nearest configuration file found by
+/// searching up from the file's directory. The search stops once the root directory, or a directory which is not
+/// inside of the root directory, has been checked.
+/// If no configuration file is found, any `.editorconfig` files found searching up from the file's directory
+/// are used instead, stopping at one marked with `root = true`.
+/// Configurations are cached per directory, so each directory is only searched once.
+pub struct ConfigResolver {
+    root: PathBuf,
+    config_path: Option<PathBuf>,
+    cache: HashMap<PathBuf, Option<Arc<ConfigFile>>>,
+    editorconfig_cache: HashMap<PathBuf, Arc<ConfigFile>>,
+}
+
+impl ConfigResolver {
+    /// Creates a new resolver which searches up to the given root directory.
+    /// If a configuration file path is given, it is used for every file instead.
+    pub fn new(root: PathBuf, config_path: Option<PathBuf>) -> Self {
+        Self {
+            root: normalize_path(&root),
+            config_path,
+            cache: HashMap::new(),
+            editorconfig_cache: HashMap::new(),
+        }
+    }
+
+    /// Removes any cached configurations, so that they are read again on next use
+    pub fn clear(&mut self) {
+        self.cache.clear();
+        self.editorconfig_cache.clear();
+    }
+
+    /// Returns the configuration to use for the file at the given path, including any overrides which match it
+    pub fn for_file(&mut self, path: &Path) -> Result<Config> {
+        let path = normalize_path(&std::env::current_dir()?.join(path));
+        let directory = path.parent().unwrap_or(&path);
+        Ok(self.config_file(directory)?.config_for(&path))
+    }
+
+    /// Returns the configuration to use for code read from stdin
+    pub fn for_stdin(&mut self) -> Result<Config> {
+        self.for_file(Path::new(STDIN_FILE_NAME))
+    }
+
+    fn config_file(&mut self, directory: &Path) -> Result<Arc<ConfigFile>> {
+        if let Some(config_path) = self.config_path.to_owned() {
+            if let Some(Some(config_file)) = self.cache.get(&config_path) {
+                return Ok(Arc::clone(config_file));
+            }
+
+            let config_file = Arc::new(read_from_path(
+                &std::env::current_dir()?.join(&config_path),
+            )?);
+            self.cache
+                .insert(config_path, Some(Arc::clone(&config_file)));
+            return Ok(config_file);
+        }
+
+        match self.search(directory)? {
+            Some(config_file) => Ok(config_file),
+            None => self.search_editorconfig(directory),
+        }
+    }
+
+    /// Searches for the nearest stylua.toml, up to the root directory.
+    /// The search never leaves the root directory, so files outside of it only use a configuration file in their own directory
+    fn search(&mut self, directory: &Path) -> Result<Option<Arc<ConfigFile>>> {
+        if let Some(config_file) = self.cache.get(directory) {
+            return Ok(config_file.to_owned());
+        }
+
+        let config_path = CONFIG_FILE_NAMES
+            .iter()
+            .map(|name| directory.join(name))
+            .find(|path| path.is_file());
+
+        let config_file = match config_path {
+            Some(path) => Some(Arc::new(read_from_path(&path)?)),
+            None => match directory.parent() {
+                Some(parent) if directory != self.root && directory.starts_with(&self.root) => {
+                    self.search(parent)?
+                }
+                _ => None,
+            },
+        };
+
+        self.cache
+            .insert(directory.to_owned(), config_file.to_owned());
+        Ok(config_file)
+    }
+
+    /// Combines the sections of every `.editorconfig` file which applies to the directory.
+    /// Sections in files closer to the directory come later, so take precedence.
+    fn search_editorconfig(&mut self, directory: &Path) -> Result<Arc<ConfigFile>> {
+        if let Some(config_file) = self.editorconfig_cache.get(directory) {
+            return Ok(Arc::clone(config_file));
+        }
+
+        let (root, mut sections) = read_editorconfig(directory)?.unwrap_or_default();
+        let mut overrides = match directory.parent() {
+            Some(parent) if !root => self.search_editorconfig(parent)?.overrides.to_owned(),
+            _ => Vec::new(),
+        };
+        overrides.append(&mut sections);
+
+        let config_file = Arc::new(ConfigFile {
+            config: Config::default(),
+            overrides,
+        });
+        self.editorconfig_cache
+            .insert(directory.to_owned(), Arc::clone(&config_file));
+        Ok(config_file)
+    }
+}
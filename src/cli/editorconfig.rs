@@ -0,0 +1,104 @@
+use crate::config::PartialConfig;
+use std::collections::HashMap;
+use stylua_lib::{IndentType, LineEndings, QuoteStyle};
+
+/// A section of an `.editorconfig` file, whose properties apply to any files matching its glob
+pub struct Section {
+    /// The glob to match against paths relative to the directory containing the `.editorconfig` file
+    pub glob: String,
+    pub config: PartialConfig,
+}
+
+/// The contents of an `.editorconfig` file
+pub struct EditorConfig {
+    /// Whether this is the top-most `.editorconfig` file, so that no parent directories should be searched
+    pub root: bool,
+    pub sections: Vec<Section>,
+}
+
+/// Converts the name of a section into a glob.
+/// Names without a `/` match files with that name in any directory, otherwise they are relative to the `.editorconfig` file.
+fn section_glob(name: &str) -> String {
+    if name.contains('/') {
+        name.strip_prefix('/').unwrap_or(name).to_owned()
+    } else {
+        format!("**/{}", name)
+    }
+}
+
+/// Maps the EditorConfig properties StyLua understands onto its options.
+/// Any unknown properties, or values which cannot be used, are ignored.
+fn section_config(properties: &HashMap<String, String>) -> PartialConfig {
+    let get = |key: &str| properties.get(key).map(String::as_str);
+    let tab_width = get("tab_width").and_then(|value| value.parse().ok());
+
+    PartialConfig {
+        column_width: get("max_line_length").and_then(|value| value.parse().ok()),
+        line_endings: match get("end_of_line") {
+            Some("lf") => Some(LineEndings::Unix),
+            Some("crlf") => Some(LineEndings::Windows),
+            _ => None,
+        },
+        indent_type: match get("indent_style") {
+            Some("tab") => Some(IndentType::Tabs),
+            Some("space") => Some(IndentType::Spaces),
+            _ => None,
+        },
+        indent_width: match get("indent_size") {
+            Some("tab") | None => tab_width,
+            Some(value) => value.parse().ok(),
+        },
+        quote_style: match get("quote_type") {
+            Some("single") => Some(QuoteStyle::AutoPreferSingle),
+            Some("double") | Some("auto") => Some(QuoteStyle::AutoPreferDouble),
+            _ => None,
+        },
+        ..PartialConfig::default()
+    }
+}
+
+/// Parses the contents of an `.editorconfig` file. Any lines which cannot be understood are ignored.
+pub fn parse(content: &str) -> EditorConfig {
+    let mut root = false;
+    let mut sections = Vec::new();
+    let mut current_section: Option<(String, HashMap<String, String>)> = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            if let Some((glob, properties)) = current_section.take() {
+                sections.push(Section {
+                    glob,
+                    config: section_config(&properties),
+                });
+            }
+
+            current_section = Some((section_glob(&line[1..line.len() - 1]), HashMap::new()));
+        } else if let Some((key, value)) = line.split_once('=') {
+            // Keys and values are case insensitive
+            let key = key.trim().to_lowercase();
+            let value = value.trim().to_lowercase();
+
+            match &mut current_section {
+                Some((_, properties)) => {
+                    properties.insert(key, value);
+                }
+                None if key == "root" => root = value == "true",
+                None => (),
+            }
+        }
+    }
+
+    if let Some((glob, properties)) = current_section {
+        sections.push(Section {
+            glob,
+            config: section_config(&properties),
+        });
+    }
+
+    EditorConfig { root, sections }
+}
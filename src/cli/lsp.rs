@@ -0,0 +1,384 @@
+use anyhow::{bail, format_err, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use stylua_lib::{check_syntax, format_code, Config, Error, Range};
+use url::Url;
+
+use crate::config::ConfigResolver;
+
+// JSON-RPC error codes
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const REQUEST_FAILED: i64 = -32803;
+
+// LSP `TextDocumentSyncKind.Full`
+const TEXT_DOCUMENT_SYNC_FULL: u8 = 1;
+// LSP `DiagnosticSeverity.Error`
+const SEVERITY_ERROR: u8 = 1;
+// LSP `MessageType.Error`
+const MESSAGE_TYPE_ERROR: u8 = 1;
+
+/// Reads a single message from the client, returning `None` if the input has been closed
+fn read_message(input: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(length) = header.strip_prefix("Content-Length:") {
+            content_length = Some(length.trim().parse::<usize>()?);
+        }
+    }
+
+    let content_length = match content_length {
+        Some(content_length) => content_length,
+        None => bail!("error: message is missing a Content-Length header"),
+    };
+
+    let mut content = vec![0; content_length];
+    input.read_exact(&mut content)?;
+    Ok(Some(serde_json::from_slice(&content)?))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> Result<()> {
+    let content = message.to_string();
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Converts an LSP position (a line and a UTF-16 offset within it) into a byte offset within the text
+fn offset_at(text: &str, position: &Value) -> Option<usize> {
+    let line = position.get("line")?.as_u64()? as usize;
+    let character = position.get("character")?.as_u64()? as usize;
+
+    let mut offset = 0;
+    let mut lines = text.split_inclusive('\n');
+    for _ in 0..line {
+        offset += lines.next()?.len();
+    }
+
+    let mut column = 0;
+    for (index, c) in text[offset..].char_indices() {
+        if column >= character || c == '\n' {
+            return Some(offset + index);
+        }
+        column += c.len_utf16();
+    }
+
+    Some(text.len())
+}
+
+/// Converts a position given in a request into a byte offset within the text.
+/// Positions which are missing or outside of the text are invalid, rather than being treated as the start or end of the text
+fn request_offset(text: &str, position: Option<&Value>) -> Result<usize, (i64, String)> {
+    position
+        .and_then(|position| offset_at(text, position))
+        .ok_or_else(|| (INVALID_PARAMS, String::from("invalid position")))
+}
+
+/// Converts a byte offset within the text into an LSP position
+fn position_at(text: &str, offset: usize) -> Value {
+    let before = &text[..offset];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+
+    json!({ "line": line, "character": character })
+}
+
+/// Creates the edits needed to turn the original text into the formatted text.
+/// Only the section between the common prefix and suffix is replaced, so that the client can keep the cursor in place.
+fn text_edits(original: &str, formatted: &str) -> Value {
+    if original == formatted {
+        return json!([]);
+    }
+
+    let prefix = original
+        .char_indices()
+        .zip(formatted.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(original.len().min(formatted.len()), |((index, _), _)| index);
+
+    let suffix = original[prefix..]
+        .chars()
+        .rev()
+        .zip(formatted[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum::<usize>();
+
+    json!([{
+        "range": {
+            "start": position_at(original, prefix),
+            "end": position_at(original, original.len() - suffix),
+        },
+        "newText": &formatted[prefix..formatted.len() - suffix],
+    }])
+}
+
+/// Returns the file system path of a `file://` URI, used to find its configuration.
+/// Any other URI, such as that of an unsaved document, is treated as a relative path
+fn uri_path(uri: &str) -> PathBuf {
+    Url::parse(uri)
+        .ok()
+        .and_then(|url| url.to_file_path().ok())
+        .unwrap_or_else(|| PathBuf::from(uri))
+}
+
+struct Server<F: Fn(Config) -> Config> {
+    config_resolver: ConfigResolver,
+    apply_options: F,
+    documents: HashMap<String, String>,
+    shutdown: bool,
+}
+
+impl<F: Fn(Config) -> Config> Server<F> {
+    /// Returns the uri and contents of the document a request refers to
+    fn document(&self, params: &Value) -> Result<(String, String), (i64, String)> {
+        let uri = params
+            .pointer("/textDocument/uri")
+            .and_then(Value::as_str)
+            .ok_or_else(|| (INVALID_PARAMS, String::from("missing text document uri")))?;
+
+        match self.documents.get(uri) {
+            Some(text) => Ok((uri.to_owned(), text.to_owned())),
+            None => Err((INVALID_PARAMS, format!("unknown text document {}", uri))),
+        }
+    }
+
+    /// Returns the configuration to use for the given document
+    fn config(&mut self, uri: &str) -> Result<Config> {
+        let path = uri_path(uri);
+        let config = self.config_resolver.for_file(&path)?;
+        Ok((self.apply_options)(config))
+    }
+
+    fn format(
+        &mut self,
+        uri: &str,
+        text: &str,
+        range: Option<Range>,
+    ) -> Result<Value, (i64, String)> {
+        let config = self
+            .config(uri)
+            .map_err(|error| (REQUEST_FAILED, error.to_string()))?;
+
+        match format_code(text, config, range) {
+            Ok(formatted) => Ok(text_edits(text, &formatted)),
+            // Parse errors are already reported to the client as diagnostics
+            Err(Error::Parse { .. }) => Ok(Value::Null),
+            Err(error) => Err((REQUEST_FAILED, error.to_string())),
+        }
+    }
+
+    fn handle_request(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": TEXT_DOCUMENT_SYNC_FULL,
+                    "documentFormattingProvider": true,
+                    "documentRangeFormattingProvider": true,
+                    "documentOnTypeFormattingProvider": { "firstTriggerCharacter": "\n" },
+                },
+                "serverInfo": {
+                    "name": "stylua",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/formatting" => {
+                let (uri, text) = self.document(params)?;
+                self.format(&uri, &text, None)
+            }
+            "textDocument/rangeFormatting" => {
+                let (uri, text) = self.document(params)?;
+                let start = request_offset(&text, params.pointer("/range/start"))?;
+                let end = request_offset(&text, params.pointer("/range/end"))?;
+                self.format(
+                    &uri,
+                    &text,
+                    Some(Range::from_values(Some(start), Some(end))),
+                )
+            }
+            "textDocument/onTypeFormatting" => {
+                // Format the line which has just been finished
+                let (uri, text) = self.document(params)?;
+                let line = params
+                    .pointer("/position/line")
+                    .and_then(Value::as_u64)
+                    .ok_or_else(|| (INVALID_PARAMS, String::from("missing position")))?;
+                if line == 0 {
+                    return Ok(Value::Null);
+                }
+
+                let start =
+                    request_offset(&text, Some(&json!({ "line": line - 1, "character": 0 })))?;
+                let end = request_offset(&text, Some(&json!({ "line": line, "character": 0 })))?;
+                self.format(
+                    &uri,
+                    &text,
+                    Some(Range::from_values(Some(start), Some(end))),
+                )
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method {}", method))),
+        }
+    }
+
+    /// Handles a notification, returning any notifications to send back to the client
+    fn handle_notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        match method {
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                if let (Some(uri), Some(text)) =
+                    (document["uri"].as_str(), document["text"].as_str())
+                {
+                    self.documents.insert(uri.to_owned(), text.to_owned());
+                    return vec![self.diagnostics(uri)];
+                }
+                vec![]
+            }
+            "textDocument/didChange" => {
+                // We only support full document syncing, so the last change contains the whole document
+                let uri = params.pointer("/textDocument/uri").and_then(Value::as_str);
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+
+                if let (Some(uri), Some(text)) = (uri, text) {
+                    self.documents.insert(uri.to_owned(), text.to_owned());
+                    return vec![self.diagnostics(uri)];
+                }
+                vec![]
+            }
+            "textDocument/didClose" => {
+                if let Some(uri) = params.pointer("/textDocument/uri").and_then(Value::as_str) {
+                    self.documents.remove(uri);
+                    return vec![json!({
+                        "jsonrpc": "2.0",
+                        "method": "textDocument/publishDiagnostics",
+                        "params": { "uri": uri, "diagnostics": [] },
+                    })];
+                }
+                vec![]
+            }
+            "workspace/didChangeConfiguration" => {
+                // Configuration files are read again when next needed, which may change whether documents can be parsed
+                self.config_resolver.clear();
+                let uris: Vec<String> = self.documents.keys().cloned().collect();
+                uris.iter().map(|uri| self.diagnostics(uri)).collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Creates a `publishDiagnostics` notification for the given document, reporting any parse error.
+    /// If the configuration for the document cannot be read, the error is shown to the user instead.
+    fn diagnostics(&mut self, uri: &str) -> Value {
+        if let Err(error) = self.config(uri) {
+            return json!({
+                "jsonrpc": "2.0",
+                "method": "window/showMessage",
+                "params": { "type": MESSAGE_TYPE_ERROR, "message": error.to_string() },
+            });
+        }
+
+        let text = &self.documents[uri];
+        let diagnostics = match check_syntax(text) {
+            Err(Error::Parse {
+                message,
+                byte_range: (start, end),
+                ..
+            }) => json!([{
+                "range": {
+                    "start": position_at(text, start.min(text.len())),
+                    "end": position_at(text, end.min(text.len())),
+                },
+                "severity": SEVERITY_ERROR,
+                "source": "stylua",
+                "message": message,
+            }]),
+            _ => json!([]),
+        };
+
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        })
+    }
+}
+
+/// Runs the language server over stdin and stdout until the client exits.
+/// Configuration files are found for each document in the same way as when formatting files,
+/// and are read again whenever the client notifies us that the configuration has changed.
+pub fn run(
+    config_resolver: ConfigResolver,
+    apply_options: impl Fn(Config) -> Config,
+) -> Result<i32> {
+    let mut server = Server {
+        config_resolver,
+        apply_options,
+        documents: HashMap::new(),
+        shutdown: false,
+    };
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut input = stdin.lock();
+    let mut output = stdout.lock();
+
+    while let Some(message) = read_message(&mut input)? {
+        let method = match message["method"].as_str() {
+            Some(method) => method,
+            // We never send requests to the client, so ignore any responses
+            None => continue,
+        };
+
+        if method == "exit" {
+            return Ok(if server.shutdown { 0 } else { 1 });
+        }
+
+        let params = &message["params"];
+        match message.get("id") {
+            Some(id) => {
+                let response = match server.handle_request(method, params) {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err((code, message)) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": code, "message": message },
+                    }),
+                };
+                write_message(&mut output, &response)?;
+            }
+            None => {
+                for notification in server.handle_notification(method, params) {
+                    write_message(&mut output, &notification)?;
+                }
+            }
+        }
+    }
+
+    Err(format_err!(
+        "error: client closed the connection without exiting"
+    ))
+}
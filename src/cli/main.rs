@@ -1,21 +1,38 @@
 use anyhow::{format_err, Result};
-use ignore::{overrides::OverrideBuilder, WalkBuilder};
+use ignore::{gitignore::GitignoreBuilder, overrides::OverrideBuilder, Match, WalkBuilder};
 use std::fs;
 use std::io::{stdin, stdout, Read, Write};
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
 use structopt::{clap::arg_enum, StructOpt};
 use stylua_lib::{format_code, Config, Range};
 
+mod cache;
 mod config;
+mod editorconfig;
+mod lsp;
+
+use cache::Cache;
+use config::ConfigResolver;
 mod output_diff;
 
+use output_diff::DiffReporter;
+
 #[derive(StructOpt, Debug)]
 #[structopt(name = "stylua", about = "A utility to format Lua code")]
 struct Opt {
-    /// Specify path to stylua.toml configuration file
+    /// Specify path to stylua.toml configuration file.
+    /// If not provided, the nearest stylua.toml or .stylua.toml found searching up from each file is used
     #[structopt(long = "config-path", parse(from_os_str))]
     config_path: Option<PathBuf>,
 
+    /// The directory to stop searching for configuration files at.
+    /// Defaults to the current working directory
+    #[structopt(long = "config-root", parse(from_os_str))]
+    config_root: Option<PathBuf>,
+
     /// Runs in 'check' mode.
     /// Exits with 0 if all formatting is OK,
     /// Exits with 1 if the formatting is incorrect.
@@ -23,6 +40,20 @@ struct Opt {
     #[structopt(short, long)]
     check: bool,
 
+    /// Verifies the formatted output by reparsing it and checking it has the same meaning as the input.
+    /// Any file which fails verification will not be written to.
+    #[structopt(long)]
+    verify: bool,
+
+    /// The format to output the differences found in check mode in.
+    /// `json`, `checkstyle`, `github` and `unified` are machine-readable formats for use in CI
+    #[structopt(long, possible_values = &OutputFormat::variants(), case_insensitive = true, default_value = "standard")]
+    output_format: OutputFormat,
+
+    /// The number of unchanged lines to show around each difference found in check mode
+    #[structopt(long, default_value = "3")]
+    diff_context: usize,
+
     // Whether the output should include terminal colour or not
     #[structopt(long, possible_values = &Color::variants(), case_insensitive = true, default_value = "auto")]
     color: Color,
@@ -32,6 +63,11 @@ struct Opt {
     #[structopt(short, long)]
     glob: Option<Vec<String>>,
 
+    /// Runs a language server over stdio, formatting documents sent by the editor.
+    /// Any files given are ignored.
+    #[structopt(long)]
+    lsp: bool,
+
     /// A starting range to format files, given as a byte offset from the beginning of the file.
     /// Any content before this value will be ignored.
     #[structopt(long)]
@@ -42,6 +78,30 @@ struct Opt {
     #[structopt(long)]
     range_end: Option<usize>,
 
+    /// Caches the files which are already formatted, so they are skipped until they or their configuration change.
+    /// The cache is stored in `.stylua-cache`, or under `$XDG_CACHE_HOME` if it is set
+    #[structopt(long)]
+    cache: bool,
+
+    /// The path to store the cache at. Implies `--cache`
+    #[structopt(long, parse(from_os_str))]
+    cache_location: Option<PathBuf>,
+
+    /// Disables the cache, even if `--cache` is given
+    #[structopt(long)]
+    no_cache: bool,
+
+    /// The path of the file being formatted when reading from stdin.
+    /// It is used to find the configuration to use, and if the path is ignored or excluded by `--glob` the code is output unchanged.
+    /// In check mode, any differences are reported under this path
+    #[structopt(long, parse(from_os_str))]
+    stdin_filepath: Option<PathBuf>,
+
+    /// The number of threads to format files on.
+    /// Defaults to the number of CPUs available
+    #[structopt(long)]
+    num_threads: Option<usize>,
+
     /// A list of files to format
     #[structopt(parse(from_os_str))]
     files: Vec<PathBuf>,
@@ -56,16 +116,84 @@ structopt::clap::arg_enum! {
     }
 }
 
+structopt::clap::arg_enum! {
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum OutputFormat {
+        Standard,
+        Json,
+        Checkstyle,
+        Github,
+        Unified,
+    }
+}
+
+/// Resolves any `.` and `..` components of an absolute path, without requiring the path to exist
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Returns whether the file at the given absolute path is ignored by a `.styluaignore` file in one of its parent directories.
+/// As when searching for files, ignore files closer to the path take precedence over those further up
+fn is_ignored(path: &Path) -> Result<bool> {
+    for directory in path.ancestors().skip(1) {
+        let ignore_path = directory.join(".styluaignore");
+        if !ignore_path.is_file() {
+            continue;
+        }
+
+        let mut builder = GitignoreBuilder::new(directory);
+        if let Some(error) = builder.add(&ignore_path) {
+            return Err(format_err!(
+                "error: could not read {}: {}",
+                ignore_path.display(),
+                error
+            ));
+        }
+        let ignore = builder.build().map_err(|error| {
+            format_err!("error: could not read {}: {}", ignore_path.display(), error)
+        })?;
+
+        match ignore.matched_path_or_any_parents(path, false) {
+            Match::Ignore(_) => return Ok(true),
+            Match::Whitelist(_) => return Ok(false),
+            Match::None => continue,
+        }
+    }
+
+    Ok(false)
+}
+
+/// Formats a file, writing the formatted code back to it.
+/// In check mode, the file is left as it is, and its original and formatted code are returned if they differ.
+/// Files which the cache knows are formatted are skipped, and the cache is updated with the result
 fn format_file(
     path: &Path,
     config: Config,
     range: Option<Range>,
     check_only: bool,
-    color: Color,
-) -> Result<i32> {
+    cache: Option<&Cache>,
+) -> Result<Option<(String, String)>> {
+    let cache = cache.map(|cache| (cache, cache::hash_config(&config)));
+
     match fs::read(path) {
         Ok(contents) => {
             let contents = String::from_utf8_lossy(&contents);
+            if let Some((cache, config_hash)) = cache {
+                if cache.is_formatted(path, config_hash, &contents) {
+                    return Ok(None);
+                }
+                cache.remove(path);
+            }
             let formatted_contents = match format_code(&contents, config, range) {
                 Ok(formatted) => formatted,
                 Err(error) => {
@@ -78,21 +206,22 @@ fn format_file(
             };
 
             if check_only {
-                let is_diff = output_diff::output_diff(
-                    &contents,
-                    &formatted_contents,
-                    3,
-                    format!("Diff in {}:", path.display()),
-                    color,
-                );
-                if is_diff {
-                    Ok(1)
+                if formatted_contents != contents {
+                    Ok(Some((contents.into_owned(), formatted_contents)))
                 } else {
-                    Ok(0)
+                    if let Some((cache, config_hash)) = cache {
+                        cache.insert(path, config_hash, &formatted_contents);
+                    }
+                    Ok(None)
                 }
             } else {
-                match fs::write(path, formatted_contents) {
-                    Ok(_) => Ok(0),
+                match fs::write(path, &formatted_contents) {
+                    Ok(_) => {
+                        if let Some((cache, config_hash)) = cache {
+                            cache.insert(path, config_hash, &formatted_contents);
+                        }
+                        Ok(None)
+                    }
                     Err(error) => Err(format_err!(
                         "error: could not write to file {}: {}",
                         path.display(),
@@ -109,31 +238,93 @@ fn format_file(
     }
 }
 
+/// Formats files concurrently on the given number of threads, returning the result for each file in the same order.
+/// Threads take the next file to format from a shared queue, so that a few large files do not hold up the rest
+fn format_files(
+    files: &[PathBuf],
+    config_resolver: &Mutex<ConfigResolver>,
+    opt: &Opt,
+    range: Option<Range>,
+    cache: Option<&Cache>,
+    num_threads: usize,
+) -> Vec<Result<Option<(String, String)>>> {
+    let next_file = AtomicUsize::new(0);
+    let results = Mutex::new((0..files.len()).map(|_| None).collect::<Vec<_>>());
+
+    thread::scope(|scope| {
+        for _ in 0..num_threads.min(files.len()) {
+            scope.spawn(|| loop {
+                let index = next_file.fetch_add(1, Ordering::Relaxed);
+                let path = match files.get(index) {
+                    Some(path) => path,
+                    None => break,
+                };
+
+                let config = config_resolver
+                    .lock()
+                    .expect("config resolver poisoned")
+                    .for_file(path);
+                let result = config.and_then(|config| {
+                    format_file(path, apply_options(opt, config), range, opt.check, cache)
+                });
+
+                results.lock().expect("results poisoned")[index] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .expect("results poisoned")
+        .into_iter()
+        .map(|result| result.expect("file was not formatted"))
+        .collect()
+}
+
 /// Takes in a string and outputs the formatted version to stdout
 /// Used when input has been provided to stdin
 fn format_string(input: String, config: Config, range: Option<Range>) -> Result<()> {
-    let out = &mut stdout();
     let formatted_contents = match format_code(&input, config, range) {
         Ok(formatted) => formatted,
         Err(error) => return Err(format_err!("error: could not format from stdin: {}", error)),
     };
 
-    match out.write_all(&formatted_contents.into_bytes()) {
+    output_string(&formatted_contents)
+}
+
+/// Writes the given code to stdout
+fn output_string(contents: &str) -> Result<()> {
+    match stdout().write_all(contents.as_bytes()) {
         Ok(()) => Ok(()),
         Err(error) => Err(format_err!("error: could not output to stdout: {}", error)),
     }
 }
 
+/// Applies any options given on the command line on top of the configuration read from a file
+fn apply_options(opt: &Opt, mut config: Config) -> Config {
+    if opt.verify {
+        config = config.with_verify(true);
+    }
+
+    config
+}
+
 fn format(opt: Opt) -> Result<i32> {
+    let cwd = std::env::current_dir()?;
+    let config_root = match &opt.config_root {
+        Some(config_root) => cwd.join(config_root),
+        None => cwd.to_owned(),
+    };
+    let mut config_resolver = ConfigResolver::new(config_root, opt.config_path.to_owned());
+
+    if opt.lsp {
+        return lsp::run(config_resolver, |config| apply_options(&opt, config));
+    }
+
     if opt.files.is_empty() {
         return Err(format_err!("error: no files provided"));
     }
 
-    let config: Config = match opt.config_path {
-        Some(path) => config::read_from_path(&path)?,
-        None => config::read()?,
-    };
-
     // Create range if provided
     let range = if opt.range_start.is_some() || opt.range_end.is_some() {
         Some(Range::from_values(opt.range_start, opt.range_end))
@@ -141,10 +332,27 @@ fn format(opt: Opt) -> Result<i32> {
         None
     };
 
+    // The path of the file which code read from stdin belongs to, if given
+    let stdin_filepath = opt
+        .stdin_filepath
+        .as_ref()
+        .map(|path| normalize_path(&cwd.join(path)));
+    // The name to report any differences found in code read from stdin under in check mode
+    let stdin_name = match &opt.stdin_filepath {
+        Some(path) => path.display().to_string(),
+        None => String::from("stdin"),
+    };
+
     let mut errors = vec![];
     let mut error_code = 0;
-
-    let cwd = std::env::current_dir()?;
+    let mut reporter = DiffReporter::new(
+        opt.output_format,
+        opt.diff_context,
+        opt.color,
+        cwd.to_owned(),
+    );
+    // The files to format, which are formatted together once the walk is complete
+    let mut files = Vec::new();
 
     // Build WalkBuilder with the files given, using any overrides set
     let mut walker_builder = WalkBuilder::new(&opt.files[0]);
@@ -158,12 +366,14 @@ fn format(opt: Opt) -> Result<i32> {
         .parents(true)
         .add_custom_ignore_filename(".styluaignore");
 
-    let use_default_glob = match opt.glob {
+    // Any overrides built from the glob patterns given, which also apply to the path given for code read from stdin
+    let mut glob_overrides = None;
+    let use_default_glob = match &opt.glob {
         Some(globs) => {
             // Build overriders with any patterns given
-            let mut overrides = OverrideBuilder::new(cwd);
+            let mut overrides = OverrideBuilder::new(&cwd);
             for pattern in globs {
-                match overrides.add(&pattern) {
+                match overrides.add(pattern) {
                     Ok(_) => continue,
                     Err(err) => errors.push(format_err!(
                         "error: cannot parse glob pattern {}: {}",
@@ -173,7 +383,8 @@ fn format(opt: Opt) -> Result<i32> {
                 }
             }
             let overrides = overrides.build()?;
-            walker_builder.overrides(overrides);
+            walker_builder.overrides(overrides.clone());
+            glob_overrides = Some(overrides);
             // We shouldn't use the default glob anymore
             false
         }
@@ -186,42 +397,84 @@ fn format(opt: Opt) -> Result<i32> {
         match result {
             Ok(entry) => {
                 if entry.is_stdin() {
-                    if opt.check {
-                        errors.push(format_err!(
-                            "warning: `--check` cannot be used whilst reading from stdin"
-                        ))
-                    };
-
                     let mut buf = String::new();
-                    match stdin().read_to_string(&mut buf) {
-                        Ok(_) => match format_string(buf, config, range) {
-                            Ok(_) => continue,
-                            Err(error) => errors.push(error),
-                        },
+                    if let Err(error) = stdin().read_to_string(&mut buf) {
+                        errors.push(format_err!("error: could not read from stdin: {}", error));
+                        continue;
+                    }
+
+                    // Code for a file which is ignored, or excluded by the glob patterns given, is output unchanged,
+                    // or passes the check
+                    if let Some(path) = &stdin_filepath {
+                        let excluded = matches!(
+                            &glob_overrides,
+                            Some(overrides) if overrides.matched(path, false).is_ignore()
+                        );
+                        match is_ignored(path).map(|ignored| ignored || excluded) {
+                            Ok(false) => (),
+                            Ok(true) => {
+                                if !opt.check {
+                                    if let Err(error) = output_string(&buf) {
+                                        errors.push(error);
+                                    }
+                                }
+                                continue;
+                            }
+                            Err(error) => {
+                                errors.push(error);
+                                continue;
+                            }
+                        }
+                    }
+
+                    let config = match &stdin_filepath {
+                        Some(path) => config_resolver
+                            .for_file(path)
+                            .map(|config| apply_options(&opt, config)),
+                        None => config_resolver
+                            .for_stdin()
+                            .map(|config| apply_options(&opt, config)),
+                    };
+                    let config = match config {
+                        Ok(config) => config,
                         Err(error) => {
-                            errors.push(format_err!("error: could not read from stdin: {}", error))
+                            errors.push(error);
+                            continue;
                         }
+                    };
+
+                    if opt.check {
+                        match format_code(&buf, config, range) {
+                            Ok(formatted_contents) => {
+                                if reporter.report(&stdin_name, &buf, &formatted_contents) {
+                                    error_code = 1;
+                                }
+                            }
+                            Err(error) => errors
+                                .push(format_err!("error: could not format from stdin: {}", error)),
+                        }
+                    } else if let Err(error) = format_string(buf, config, range) {
+                        errors.push(error);
                     }
                 } else {
                     let path = entry.path();
                     if path.is_file() {
                         // If the user didn't provide a glob pattern, we should match against our default one
                         if use_default_glob {
+                            // Luau files can only be parsed when StyLua is built with Luau support
+                            const DEFAULT_GLOB_PATTERN: &str = if cfg!(feature = "luau") {
+                                "**/*.{lua,luau}"
+                            } else {
+                                "**/*.lua"
+                            };
                             lazy_static::lazy_static! {
-                                static ref DEFAULT_GLOB: globset::GlobMatcher = globset::Glob::new("**/*.lua").expect("cannot create default glob").compile_matcher();
+                                static ref DEFAULT_GLOB: globset::GlobMatcher = globset::Glob::new(DEFAULT_GLOB_PATTERN).expect("cannot create default glob").compile_matcher();
                             }
                             if !DEFAULT_GLOB.is_match(path) {
                                 continue;
                             }
                         }
-                        match format_file(path, config, range, opt.check, opt.color) {
-                            Ok(code) => {
-                                if code != 0 {
-                                    error_code = code
-                                }
-                            }
-                            Err(error) => errors.push(error),
-                        }
+                        files.push(path.to_owned());
                     }
                 }
             }
@@ -231,6 +484,57 @@ fn format(opt: Opt) -> Result<i32> {
         }
     }
 
+    // Format the files in path order, so that the output is the same on every run
+    files.sort();
+    files.dedup();
+
+    let num_threads = match opt.num_threads {
+        Some(num_threads) => num_threads.max(1),
+        None => thread::available_parallelism().map_or(1, |threads| threads.get()),
+    };
+    // Files formatted within a range may not be fully formatted, so are never cached
+    let cache = if (opt.cache || opt.cache_location.is_some()) && !opt.no_cache && range.is_none() {
+        let location = match &opt.cache_location {
+            Some(location) => cwd.join(location),
+            None => cache::default_location(&cwd),
+        };
+        Some(Cache::load(location, cwd.to_owned()))
+    } else {
+        None
+    };
+
+    let config_resolver = Mutex::new(config_resolver);
+    let results = format_files(
+        &files,
+        &config_resolver,
+        &opt,
+        range,
+        cache.as_ref(),
+        num_threads,
+    );
+
+    if let Some(cache) = cache {
+        if let Err(error) = cache.save() {
+            errors.push(error);
+        }
+    }
+
+    for (path, result) in files.iter().zip(results) {
+        match result {
+            Ok(Some((contents, formatted_contents))) => {
+                if reporter.report(&path.display().to_string(), &contents, &formatted_contents) {
+                    error_code = 1;
+                }
+            }
+            Ok(None) => (),
+            Err(error) => errors.push(error),
+        }
+    }
+
+    if opt.check {
+        reporter.finish();
+    }
+
     if !errors.is_empty() {
         for error in errors.iter() {
             eprintln!("{}", error.to_string());
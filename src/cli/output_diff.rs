@@ -1,8 +1,12 @@
 // Based off https://github.com/mitsuhiko/similar/blob/main/examples/terminal-inline.rs
 // Licensed under https://github.com/mitsuhiko/similar/blob/main/LICENSE
 use console::{style, Style, Term};
-use similar::{ChangeTag, TextDiff};
+use similar::{ChangeTag, DiffOp, DiffTag, TextDiff};
 use std::fmt;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+
+use crate::{normalize_path, OutputFormat};
 
 struct Line(Option<usize>);
 
@@ -87,3 +91,268 @@ pub fn output_diff(
 
     true
 }
+
+/// A group of changes between the original and formatted code, alongside the unchanged lines around them
+struct Hunk {
+    /// The lines of the original code covered by the hunk, counting from zero
+    old_range: Range<usize>,
+    /// The lines of the formatted code covered by the hunk, counting from zero
+    new_range: Range<usize>,
+    /// The lines of the original code which were changed, without the unchanged lines around them
+    changed_old_range: Range<usize>,
+    /// The formatted code which replaces the changed lines
+    formatted: String,
+    /// The lines of the hunk in the unified diff format, each prefixed with ` `, `-` or `+`
+    patch: String,
+}
+
+/// Formats the start and length of a range of lines in a unified diff hunk header.
+/// Lines are counted from one, and an empty range starts at the line before it
+fn unified_range(range: &Range<usize>) -> String {
+    let length = range.end - range.start;
+    let start = if length == 0 {
+        range.start
+    } else {
+        range.start + 1
+    };
+    format!("{},{}", start, length)
+}
+
+impl Hunk {
+    fn new(diff: &TextDiff<str>, ops: &[DiffOp]) -> Self {
+        let first = &ops[0];
+        let last = &ops[ops.len() - 1];
+
+        // Every group contains at least one change, surrounded by the unchanged context lines
+        let changed_ops: Vec<&DiffOp> =
+            ops.iter().filter(|op| op.tag() != DiffTag::Equal).collect();
+        let first_changed = changed_ops[0];
+        let last_changed = changed_ops[changed_ops.len() - 1];
+        let changed_new_range = first_changed.new_range().start..last_changed.new_range().end;
+
+        let mut patch = String::new();
+        for op in ops {
+            for change in diff.iter_changes(op) {
+                patch += &format!("{}{}", change.tag(), change.to_string_lossy());
+                if change.missing_newline() {
+                    patch += "\n\\ No newline at end of file\n";
+                }
+            }
+        }
+
+        Self {
+            old_range: first.old_range().start..last.old_range().end,
+            new_range: first.new_range().start..last.new_range().end,
+            changed_old_range: first_changed.old_range().start..last_changed.old_range().end,
+            formatted: diff.new_slices()[changed_new_range].concat(),
+            patch,
+        }
+    }
+
+    /// The header of the hunk in the unified diff format
+    fn header(&self) -> String {
+        format!(
+            "@@ -{} +{} @@",
+            unified_range(&self.old_range),
+            unified_range(&self.new_range)
+        )
+    }
+
+    /// The first and last lines of the original code which were changed, counting from one.
+    /// When lines were only added, this is the line they were added after
+    fn changed_lines(&self) -> (usize, usize) {
+        let Range { start, end } = self.changed_old_range;
+        if start == end {
+            (start.max(1), start.max(1))
+        } else {
+            (start + 1, end)
+        }
+    }
+
+    /// A description of the change, for formats which annotate the original code
+    fn message(&self) -> String {
+        if self.formatted.is_empty() {
+            String::from("Code should be removed")
+        } else {
+            format!(
+                "Code should be formatted as:\n{}",
+                self.formatted.trim_end()
+            )
+        }
+    }
+}
+
+/// Escapes text for use in an XML attribute
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+        .replace('\r', "&#13;")
+        .replace('\n', "&#10;")
+}
+
+/// Escapes the message of a GitHub Actions workflow command
+fn escape_github_message(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a property, such as the file name, of a GitHub Actions workflow command
+fn escape_github_property(text: &str) -> String {
+    escape_github_message(text)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+/// Returns the path of a file relative to the working directory, separated with `/` and without any `.` components,
+/// as `git apply` requires in the headers of a unified diff
+fn unified_path(path: &str, cwd: &Path) -> String {
+    let path = normalize_path(&cwd.join(path));
+    let common = path
+        .components()
+        .zip(cwd.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let relative: PathBuf = cwd
+        .components()
+        .skip(common)
+        .map(|_| Component::ParentDir)
+        .chain(path.components().skip(common))
+        .collect();
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Reports the differences between the original and formatted code of each file checked, in the chosen output format
+pub struct DiffReporter {
+    format: OutputFormat,
+    context_size: usize,
+    color: crate::Color,
+    /// The working directory, which paths in a unified diff are relative to
+    cwd: PathBuf,
+    /// The report for each file, for formats which describe every file in a single document
+    files: Vec<String>,
+}
+
+impl DiffReporter {
+    pub fn new(
+        format: OutputFormat,
+        context_size: usize,
+        color: crate::Color,
+        cwd: PathBuf,
+    ) -> Self {
+        Self {
+            format,
+            context_size,
+            color,
+            cwd,
+            files: Vec::new(),
+        }
+    }
+
+    /// Reports any differences between the original and formatted code of a file.
+    /// Returns whether there were any differences
+    pub fn report(&mut self, path: &str, old: &str, new: &str) -> bool {
+        if let OutputFormat::Standard = self.format {
+            return output_diff(
+                old,
+                new,
+                self.context_size,
+                format!("Diff in {}:", path),
+                self.color,
+            );
+        }
+
+        let diff = TextDiff::from_lines(old, new);
+        let hunks: Vec<Hunk> = diff
+            .grouped_ops(self.context_size)
+            .iter()
+            .map(|ops| Hunk::new(&diff, ops))
+            .collect();
+
+        if hunks.is_empty() {
+            return false;
+        }
+
+        match self.format {
+            OutputFormat::Standard => unreachable!("standard diffs are output above"),
+            OutputFormat::Json => {
+                let hunks: Vec<serde_json::Value> = hunks
+                    .iter()
+                    .map(|hunk| {
+                        serde_json::json!({
+                            "original_start": hunk.old_range.start + 1,
+                            "original_lines": hunk.old_range.len(),
+                            "formatted_start": hunk.new_range.start + 1,
+                            "formatted_lines": hunk.new_range.len(),
+                            "diff": hunk.patch,
+                        })
+                    })
+                    .collect();
+                self.files.push(
+                    serde_json::json!({
+                        "file": path,
+                        "hunks": hunks,
+                    })
+                    .to_string(),
+                );
+            }
+            OutputFormat::Checkstyle => {
+                let mut file = format!("  <file name=\"{}\">\n", escape_xml(path));
+                for hunk in &hunks {
+                    file += &format!(
+                        "    <error line=\"{}\" severity=\"warning\" message=\"{}\" source=\"stylua\" />\n",
+                        hunk.changed_lines().0,
+                        escape_xml(&hunk.message())
+                    );
+                }
+                file += "  </file>\n";
+                self.files.push(file);
+            }
+            OutputFormat::Github => {
+                for hunk in &hunks {
+                    let (line, end_line) = hunk.changed_lines();
+                    println!(
+                        "::warning file={},line={},endLine={},title=StyLua::{}",
+                        escape_github_property(path),
+                        line,
+                        end_line,
+                        escape_github_message(&hunk.message())
+                    );
+                }
+            }
+            OutputFormat::Unified => {
+                let path = unified_path(path, &self.cwd);
+                println!("--- a/{}", path);
+                println!("+++ b/{}", path);
+                for hunk in &hunks {
+                    println!("{}", hunk.header());
+                    print!("{}", hunk.patch);
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Outputs the reports for formats which describe every file in a single document
+    pub fn finish(self) {
+        match self.format {
+            OutputFormat::Json => println!("[{}]", self.files.join(",")),
+            OutputFormat::Checkstyle => {
+                println!("<?xml version=\"1.0\" encoding=\"utf-8\"?>");
+                println!("<checkstyle version=\"4.3\">");
+                print!("{}", self.files.concat());
+                println!("</checkstyle>");
+            }
+            _ => (),
+        }
+    }
+}
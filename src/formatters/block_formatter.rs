@@ -1,15 +1,19 @@
-use crate::formatters::{
-    trivia_formatter::{
-        strip_trivia, FormatTriviaType, UpdateLeadingTrivia, UpdateTrailingTrivia, UpdateTrivia,
+use crate::{
+    formatters::{
+        ignore_directives, pad_equal_token,
+        trivia_formatter::{
+            strip_trivia, FormatTriviaType, UpdateLeadingTrivia, UpdateTrailingTrivia, UpdateTrivia,
+        },
+        trivia_util, CodeFormatter, IgnoreDirective, Range,
     },
-    trivia_util, CodeFormatter, Range,
+    SemicolonStyle,
 };
 use full_moon::ast::{
     punctuated::{Pair, Punctuated},
     Block, Expression, LastStmt, Prefix, Return, Stmt, UnOp, Value, Var,
 };
 use full_moon::node::Node;
-use full_moon::tokenizer::TokenType;
+use full_moon::tokenizer::{Symbol, TokenType};
 use full_moon::tokenizer::{Token, TokenReference};
 #[cfg(feature = "luau")]
 use std::borrow::Cow;
@@ -22,6 +26,53 @@ macro_rules! update_first_token {
         Stmt::$enum($var.$update_method(new_token))
     }};
 }
+/// Updates whether we are inside a `stylua: ignore start` region, using the directives found before a statement
+fn update_ignore_region(ignoring: bool, directives: &[IgnoreDirective]) -> bool {
+    directives
+        .iter()
+        .fold(ignoring, |ignoring, directive| match directive {
+            IgnoreDirective::Start => true,
+            IgnoreDirective::End => false,
+            IgnoreDirective::Ignore => ignoring,
+        })
+}
+
+/// Returns the width of an assignment up to its `=`, or `None` if the statement is not an assignment on a single line
+fn assignment_width_before_equals(stmt: &Stmt) -> Option<usize> {
+    if !matches!(stmt, Stmt::Assignment(_) | Stmt::LocalAssignment(_)) {
+        return None;
+    }
+
+    let mut width = 0;
+    for (index, token) in stmt.tokens().enumerate() {
+        if let TokenType::Symbol {
+            symbol: Symbol::Equal,
+        } = token.token_type()
+        {
+            return Some(width);
+        }
+
+        // The leading trivia of the statement is its indentation, which is shared by the whole run
+        let text = match index {
+            0 => {
+                token.token().to_string()
+                    + &token
+                        .trailing_trivia()
+                        .map(Token::to_string)
+                        .collect::<String>()
+            }
+            _ => token.to_string(),
+        };
+        if text.contains('\n') {
+            return None;
+        }
+        width += text.chars().count();
+    }
+
+    // A local assignment without any values, such as `local x`
+    None
+}
+
 impl CodeFormatter {
     pub fn get_token_range(token: &Token) -> Range {
         (token.start_position().bytes(), token.end_position().bytes())
@@ -395,11 +446,158 @@ impl CodeFormatter {
         }
     }
 
+    /// Determines whether a block should be collapsed onto the same line as the statement containing it,
+    /// such as `if not x then return end`. Only blocks containing a single `return`, `break` or function call
+    /// without any comments are collapsed, and only when the whole statement fits within the column width.
+    /// `header_width` is the width of the statement before the block, including its indentation.
+    /// Collapsed blocks are recorded, so that they are formatted without their own indentation and newline.
+    pub fn should_collapse_block<'ast>(
+        &mut self,
+        block: &Block<'ast>,
+        header_width: usize,
+    ) -> bool {
+        if !self.config.collapse_simple_statement || trivia_util::contains_comments(block) {
+            return false;
+        }
+
+        let formatted = match (block.stmts().count(), block.last_stmt()) {
+            (1, None) => match block.stmts().next() {
+                Some(stmt @ Stmt::FunctionCall(_)) => self.format_stmt(stmt).to_string(),
+                _ => return false,
+            },
+            (0, Some(last_stmt @ LastStmt::Return(_)))
+            | (0, Some(last_stmt @ LastStmt::Break(_))) => {
+                self.format_last_stmt(last_stmt).to_string()
+            }
+            _ => return false,
+        };
+
+        // The block is placed between the header and `end`, separated by spaces
+        let formatted = formatted.trim();
+        if formatted.contains('\n')
+            || header_width + 1 + formatted.len() + " end".len() > self.config.column_width
+        {
+            return false;
+        }
+
+        match block.range() {
+            Some((start, end)) => {
+                self.collapsed_blocks.insert((start.bytes(), end.bytes()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes the indentation and trailing newline from the statement inside of a collapsed block
+    pub fn collapse_block<'ast>(block: Block<'ast>) -> Block<'ast> {
+        let stmts = block
+            .stmts_with_semicolon()
+            .map(|(stmt, semicolon)| {
+                let stmt = match stmt {
+                    Stmt::FunctionCall(function_call) => Stmt::FunctionCall(
+                        function_call.update_leading_trivia(FormatTriviaType::Replace(vec![])),
+                    ),
+                    other => other.to_owned(),
+                };
+                let (stmt, _) = trivia_util::get_stmt_trailing_trivia(stmt);
+                let semicolon = semicolon.as_ref().map(|semicolon| {
+                    semicolon.update_trailing_trivia(FormatTriviaType::Replace(vec![]))
+                });
+                (stmt, semicolon)
+            })
+            .collect();
+
+        let last_stmt = block
+            .last_stmt_with_semicolon()
+            .map(|(last_stmt, semicolon)| {
+                let last_stmt = match last_stmt {
+                    LastStmt::Return(return_node) => {
+                        let token = return_node
+                            .token()
+                            .update_leading_trivia(FormatTriviaType::Replace(vec![]));
+                        LastStmt::Return(return_node.to_owned().with_token(token))
+                    }
+                    LastStmt::Break(token) => LastStmt::Break(
+                        token.update_leading_trivia(FormatTriviaType::Replace(vec![])),
+                    ),
+                    other => other.to_owned(),
+                };
+                let (last_stmt, _) = trivia_util::get_last_stmt_trailing_trivia(last_stmt);
+                let semicolon = semicolon.as_ref().map(|semicolon| {
+                    semicolon.update_trailing_trivia(FormatTriviaType::Replace(vec![]))
+                });
+                (last_stmt, semicolon)
+            });
+
+        block.with_stmts(stmts).with_last_stmt(last_stmt)
+    }
+
+    /// Pads the `=` of runs of consecutive assignments, so that their equals signs line up
+    fn align_assignments<'ast>(
+        &self,
+        stmts: Vec<(Stmt<'ast>, Option<TokenReference<'ast>>)>,
+        alignable: &[bool],
+    ) -> Vec<(Stmt<'ast>, Option<TokenReference<'ast>>)> {
+        let items: Vec<_> = stmts
+            .iter()
+            .zip(alignable)
+            .map(|((stmt, _), alignable)| match alignable {
+                true => assignment_width_before_equals(stmt).map(|width| {
+                    let leading_trivia = stmt.surrounding_trivia().0;
+                    let line_width = self.first_line_width(&stmt.to_string(), &leading_trivia);
+                    let after_blank_line = trivia_util::trivia_contains_blank_line(&leading_trivia);
+                    (width, line_width, after_blank_line)
+                }),
+                false => None,
+            })
+            .collect();
+        let padding = self.alignment_padding(&items);
+
+        stmts
+            .into_iter()
+            .zip(padding)
+            .map(|((stmt, semicolon), padding)| {
+                let stmt = match stmt {
+                    Stmt::Assignment(assignment) if padding > 0 => {
+                        let equal_token = pad_equal_token(assignment.equal_token(), padding);
+                        Stmt::Assignment(assignment.with_equal_token(equal_token))
+                    }
+                    Stmt::LocalAssignment(local_assignment) if padding > 0 => {
+                        let equal_token = local_assignment
+                            .equal_token()
+                            .map(|equal_token| pad_equal_token(equal_token, padding));
+                        Stmt::LocalAssignment(local_assignment.with_equal_token(equal_token))
+                    }
+                    other => other,
+                };
+                (stmt, semicolon)
+            })
+            .collect()
+    }
+
     pub fn format_block<'ast>(&mut self, block: Block<'ast>) -> Block<'ast> {
         let mut formatted_statements: Vec<(Stmt<'ast>, Option<TokenReference<'ast>>)> = Vec::new();
+        // Whether each formatted statement can be aligned with the statements around it
+        let mut alignable = Vec::new();
         let mut found_first_stmt = false;
+        // Whether we are inside a `stylua: ignore start` region. Unterminated regions extend to the end of the block
+        let mut ignoring = false;
         let mut stmt_iterator = block.stmts_with_semicolon().peekable();
         while let Some((stmt, semi)) = stmt_iterator.next() {
+            let directives = ignore_directives(stmt.surrounding_trivia().0);
+            ignoring = update_ignore_region(ignoring, &directives);
+            if ignoring || directives.contains(&IgnoreDirective::Ignore) {
+                // Leave the statement, including any blocks inside of it, exactly as it was written
+                self.add_ignored_node(stmt);
+                found_first_stmt = true;
+                formatted_statements.push((stmt.to_owned(), semi.to_owned()));
+                alignable.push(false);
+                continue;
+            }
+
+            alignable.push(self.should_format_node(stmt));
+
             let mut stmt = self.format_stmt(stmt);
 
             // If this is the first stmt, then remove any leading newlines
@@ -430,9 +628,15 @@ impl CodeFormatter {
                 _ => false,
             };
 
+            let keep_semicolon = match self.config.semicolons {
+                SemicolonStyle::Remove => require_semicolon,
+                SemicolonStyle::Preserve => require_semicolon || semi.is_some(),
+                SemicolonStyle::Always => true,
+            };
+
             // If we have a semicolon, we need to push all the trailing trivia from the statement
             // and move it to the end of the semicolon
-            let semicolon = match require_semicolon {
+            let semicolon = match keep_semicolon {
                 true => {
                     let (updated_stmt, trivia) = trivia_util::get_stmt_trailing_trivia(stmt);
                     stmt = updated_stmt;
@@ -473,16 +677,54 @@ impl CodeFormatter {
         // Drop the stmt_iterator as we do not need it anymore and we still need to use `block`
         drop(stmt_iterator);
 
+        if self.config.align_assignments {
+            formatted_statements = self.align_assignments(formatted_statements, &alignable);
+        }
+
+        let last_stmt_ignored = match block.last_stmt() {
+            Some(last_stmt) => {
+                let directives = ignore_directives(last_stmt.surrounding_trivia().0);
+                update_ignore_region(ignoring, &directives)
+                    || directives.contains(&IgnoreDirective::Ignore)
+            }
+            None => false,
+        };
+
         let formatted_last_stmt = match block.last_stmt_with_semicolon() {
+            Some((last_stmt, semi)) if last_stmt_ignored => {
+                self.add_ignored_node(last_stmt);
+                Some((last_stmt.to_owned(), semi.to_owned()))
+            }
             Some((last_stmt, semi)) => {
                 let mut last_stmt = self.format_last_stmt(last_stmt);
                 // If this is the first stmt, then remove any leading newlines
                 if !found_first_stmt && self.should_format_node(&last_stmt) {
                     last_stmt = CodeFormatter::last_stmt_remove_leading_newlines(last_stmt);
                 }
-                // LastStmt will never need a semicolon
+                // LastStmt will never need a semicolon, unless the semicolon style asks for one
+                let keep_semicolon = match self.config.semicolons {
+                    SemicolonStyle::Remove => false,
+                    SemicolonStyle::Preserve => semi.is_some(),
+                    SemicolonStyle::Always => true,
+                };
+
                 // We need to check if we previously had a semicolon, and keep the comments if so
                 let semicolon = match semi {
+                    _ if keep_semicolon => {
+                        let (updated_last_stmt, trivia) =
+                            trivia_util::get_last_stmt_trailing_trivia(last_stmt);
+                        last_stmt = updated_last_stmt;
+
+                        Some(
+                            match semi {
+                                Some(semi) => crate::fmt_symbol!(self, semi, ";"),
+                                None => {
+                                    TokenReference::symbol(";").expect("could not make semicolon")
+                                }
+                            }
+                            .update_trailing_trivia(FormatTriviaType::Append(trivia)),
+                        )
+                    }
                     Some(semi) => {
                         let (updated_last_stmt, trivia) =
                             trivia_util::get_last_stmt_trailing_trivia(last_stmt);
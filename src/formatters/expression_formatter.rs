@@ -1,8 +1,10 @@
 use full_moon::ast::{BinOp, Expression, Index, Prefix, Suffix, UnOp, Value, Var, VarExpression};
-use full_moon::tokenizer::{Symbol, TokenReference, TokenType};
+use full_moon::tokenizer::{Symbol, Token, TokenReference, TokenType};
+use std::borrow::Cow;
 use std::boxed::Box;
 
-use crate::formatters::CodeFormatter;
+use crate::formatters::{is_valid_identifier, string_literal_value, trivia_util, CodeFormatter};
+use crate::KeyStyle;
 
 #[macro_export]
 macro_rules! fmt_op {
@@ -16,6 +18,59 @@ macro_rules! fmt_op {
     };
 }
 
+/// The position of an expression within its parent, used to determine whether parentheses around it are redundant
+#[derive(Debug, Clone, Copy)]
+enum ExpressionContext {
+    /// The expression is not an operand, such as the value of an assignment or a function argument
+    Standard,
+    /// The expression is the prefix of a call or an index, such as `(a + b):foo()`, or has a type assertion,
+    /// so parentheses around operators must be kept
+    Prefix,
+    /// The expression is the operand of a unary operator.
+    /// Holds whether the operator is `-`, as `-(-x)` cannot be written as `--x`
+    UnaryOperand { minus: bool },
+    /// The expression is the left hand side of a binary operator
+    BinaryLhs {
+        precedence: u8,
+        right_associative: bool,
+    },
+    /// The expression is the right hand side of a binary operator
+    BinaryRhs {
+        precedence: u8,
+        right_associative: bool,
+    },
+}
+
+impl ExpressionContext {
+    /// Whether an operator with the given precedence and associativity can be written without parentheses in this context
+    fn allows_operator(&self, precedence: u8) -> bool {
+        match *self {
+            ExpressionContext::Standard => true,
+            ExpressionContext::Prefix => false,
+            ExpressionContext::UnaryOperand { .. } => precedence > UNARY_PRECEDENCE,
+            // Operators of the same precedence share their associativity, so `(a - b) + c` is `a - b + c`,
+            // but `(a .. b) .. c` is not `a .. b .. c`
+            ExpressionContext::BinaryLhs {
+                precedence: outer_precedence,
+                right_associative,
+            } => {
+                precedence > outer_precedence
+                    || (precedence == outer_precedence && !right_associative)
+            }
+            ExpressionContext::BinaryRhs {
+                precedence: outer_precedence,
+                right_associative,
+            } => {
+                precedence > outer_precedence
+                    || (precedence == outer_precedence && right_associative)
+            }
+        }
+    }
+}
+
+/// The precedence of all unary operators, which bind tighter than every binary operator except `^`
+const UNARY_PRECEDENCE: u8 = 7;
+
 impl CodeFormatter {
     pub fn format_binop<'ast>(&self, binop: &BinOp<'ast>) -> BinOp<'ast> {
         fmt_op!(self, BinOp, binop, {
@@ -38,17 +93,25 @@ impl CodeFormatter {
     }
 
     /// Check to determine whether expression parentheses are required, depending on the provided
-    /// internal expression contained within the parentheses
-    fn check_excess_parentheses(internal_expression: &Expression) -> bool {
+    /// internal expression contained within the parentheses, and where the parentheses are found
+    fn check_excess_parentheses(
+        internal_expression: &Expression,
+        context: ExpressionContext,
+    ) -> bool {
         match internal_expression {
             // Parentheses inside parentheses, not necessary
             Expression::Parentheses { .. } => true,
-            // Check whether the expression relating to the UnOp is safe
-            Expression::UnaryOperator { expression, .. } => {
-                CodeFormatter::check_excess_parentheses(expression)
-            }
-            // Don't bother removing them if there is a binop, as they may be needed. TODO: can we be more intelligent here?
-            Expression::BinaryOperator { .. } => false,
+            // Check whether the unary operator binds tightly enough to be used without the parentheses here
+            Expression::UnaryOperator { unop, .. } => match context {
+                // A unary operator always binds to the expression directly after it, e.g. `a ^ -b`
+                ExpressionContext::BinaryRhs { .. } => true,
+                ExpressionContext::UnaryOperand { minus } => {
+                    !(minus && matches!(unop, UnOp::Minus(_)))
+                }
+                _ => context.allows_operator(UNARY_PRECEDENCE),
+            },
+            // Check whether precedence and associativity keep the same meaning without the parentheses
+            Expression::BinaryOperator { binop, .. } => context.allows_operator(binop.precedence()),
             Expression::Value { value, .. } => {
                 match &**value {
                     // Internal expression is a function definition
@@ -80,13 +143,35 @@ impl CodeFormatter {
 
     /// Formats an Expression node
     pub fn format_expression<'ast>(&mut self, expression: &Expression<'ast>) -> Expression<'ast> {
+        self.format_expression_with_context(expression, ExpressionContext::Standard)
+    }
+
+    /// Formats an Expression node, removing any parentheses which are redundant in the given context
+    fn format_expression_with_context<'ast>(
+        &mut self,
+        expression: &Expression<'ast>,
+        context: ExpressionContext,
+    ) -> Expression<'ast> {
         match expression {
             Expression::Value {
                 value,
                 #[cfg(feature = "luau")]
                 type_assertion,
             } => Expression::Value {
-                value: Box::new(self.format_value(value)),
+                value: Box::new(match &**value {
+                    Value::ParenthesesExpression(expression) => {
+                        // A type assertion applies to the whole of the parentheses, e.g. `(a + b) :: number`
+                        #[cfg(feature = "luau")]
+                        let context = match type_assertion {
+                            Some(_) => ExpressionContext::Prefix,
+                            None => context,
+                        };
+                        Value::ParenthesesExpression(
+                            self.format_expression_with_context(expression, context),
+                        )
+                    }
+                    _ => self.format_value(value),
+                }),
                 #[cfg(feature = "luau")]
                 type_assertion: match type_assertion {
                     Some(assertion) => Some(self.format_type_assertion(assertion)),
@@ -101,10 +186,13 @@ impl CodeFormatter {
                 // If it doesn't, `use_internal_expression` will return a Some(), containing the external expression
                 // We should then return that external expression
                 // Otherwise, it will return None, and therefore we should use the original expression
-                let use_internal_expression = CodeFormatter::check_excess_parentheses(expression);
+                // Parentheses holding comments are always kept, so that the comments are not lost
+                let use_internal_expression =
+                    CodeFormatter::check_excess_parentheses(expression, context)
+                        && !trivia_util::contains_comments(contained);
 
                 if use_internal_expression {
-                    self.format_expression(expression)
+                    self.format_expression_with_context(expression, context)
                 } else {
                     Expression::Parentheses {
                         contained: self.format_contained_span(&contained),
@@ -114,12 +202,29 @@ impl CodeFormatter {
             }
             Expression::UnaryOperator { unop, expression } => Expression::UnaryOperator {
                 unop: self.format_unop(unop),
-                expression: Box::new(self.format_expression(expression)),
+                expression: Box::new(self.format_expression_with_context(
+                    expression,
+                    ExpressionContext::UnaryOperand {
+                        minus: matches!(unop, UnOp::Minus(_)),
+                    },
+                )),
             },
             Expression::BinaryOperator { lhs, binop, rhs } => Expression::BinaryOperator {
-                lhs: Box::new(self.format_expression(lhs)),
+                lhs: Box::new(self.format_expression_with_context(
+                    lhs,
+                    ExpressionContext::BinaryLhs {
+                        precedence: binop.precedence(),
+                        right_associative: binop.is_right_associative(),
+                    },
+                )),
                 binop: self.format_binop(binop),
-                rhs: Box::new(self.format_expression(rhs)),
+                rhs: Box::new(self.format_expression_with_context(
+                    rhs,
+                    ExpressionContext::BinaryRhs {
+                        precedence: binop.precedence(),
+                        right_associative: binop.is_right_associative(),
+                    },
+                )),
             },
             other => panic!("unknown node {:?}", other),
         }
@@ -131,10 +236,34 @@ impl CodeFormatter {
             Index::Brackets {
                 brackets,
                 expression,
-            } => Index::Brackets {
-                brackets: self.format_contained_span(&brackets),
-                expression: self.format_expression(expression),
-            },
+            } => {
+                let name = string_literal_value(expression)
+                    .filter(|name| is_valid_identifier(name))
+                    .filter(|_| self.config.key_style != KeyStyle::Preserve);
+
+                match name {
+                    // Write `t["name"]` as `t.name`, unless removing the brackets would lose any comments
+                    Some(name)
+                        if !trivia_util::contains_comments(brackets)
+                            && !trivia_util::contains_comments(expression) =>
+                    {
+                        Index::Dot {
+                            dot: TokenReference::symbol(".").unwrap(),
+                            name: TokenReference::new(
+                                vec![],
+                                Token::new(TokenType::Identifier {
+                                    identifier: Cow::Owned(name.to_owned()),
+                                }),
+                                vec![],
+                            ),
+                        }
+                    }
+                    _ => Index::Brackets {
+                        brackets: self.format_contained_span(&brackets),
+                        expression: self.format_expression(expression),
+                    },
+                }
+            }
 
             Index::Dot { dot, name } => Index::Dot {
                 dot: self.format_token_reference(dot),
@@ -147,9 +276,9 @@ impl CodeFormatter {
     /// Formats a Prefix Node
     pub fn format_prefix<'ast>(&mut self, prefix: &Prefix<'ast>) -> Prefix<'ast> {
         match prefix {
-            Prefix::Expression(expression) => {
-                Prefix::Expression(self.format_expression(expression))
-            }
+            Prefix::Expression(expression) => Prefix::Expression(
+                self.format_expression_with_context(expression, ExpressionContext::Prefix),
+            ),
             Prefix::Name(token_reference) => {
                 Prefix::Name(self.format_token_reference(token_reference))
             }
@@ -14,6 +14,7 @@ use crate::formatters::{
     },
     trivia_util, CodeFormatter, EndTokenType,
 };
+use crate::CallParenType;
 
 impl CodeFormatter {
     /// Formats an Anonymous Function
@@ -27,14 +28,15 @@ impl CodeFormatter {
         let additional_indent_level = self.get_range_indent_increase(function_token_range); //code_formatter.get_token_indent_increase(function_token.token());
 
         let function_token = crate::fmt_symbol!(self, function_token, "function");
-        let mut function_body = self.format_function_body(function_body, false);
+        let mut formatted_function_body = self.format_function_body(function_body, false);
 
         // Need to insert any additional trivia, as it isn't being inserted elsewhere
         #[cfg(feature = "luau")]
         {
-            let (parameters_parentheses, return_type) = match function_body.return_type() {
+            let (parameters_parentheses, return_type) = match formatted_function_body.return_type()
+            {
                 Some(return_type) => (
-                    function_body.parameters_parentheses().to_owned(),
+                    formatted_function_body.parameters_parentheses().to_owned(),
                     Some(
                         return_type.update_trailing_trivia(FormatTriviaType::Append(vec![
                             self.create_newline_trivia()
@@ -43,7 +45,7 @@ impl CodeFormatter {
                 ),
                 None => (
                     // No return type, so add trivia to the parentheses instead
-                    function_body
+                    formatted_function_body
                         .parameters_parentheses()
                         .update_trailing_trivia(FormatTriviaType::Append(vec![
                             self.create_newline_trivia()
@@ -52,28 +54,39 @@ impl CodeFormatter {
                 ),
             };
 
-            function_body = function_body
+            formatted_function_body = formatted_function_body
                 .with_parameters_parentheses(parameters_parentheses)
                 .with_return_type(return_type);
         }
 
         #[cfg(not(feature = "luau"))]
         {
-            let parameters_parentheses = function_body
+            let parameters_parentheses = formatted_function_body
                 .parameters_parentheses()
                 .update_trailing_trivia(FormatTriviaType::Append(vec![
                     self.create_newline_trivia()
                 ]));
-            function_body = function_body.with_parameters_parentheses(parameters_parentheses);
+            formatted_function_body =
+                formatted_function_body.with_parameters_parentheses(parameters_parentheses);
         };
 
-        let end_token = function_body
-            .end_token()
-            .update_leading_trivia(FormatTriviaType::Append(vec![
-                self.create_indent_trivia(additional_indent_level)
-            ]));
+        let end_token =
+            formatted_function_body
+                .end_token()
+                .update_leading_trivia(FormatTriviaType::Append(vec![
+                    self.create_indent_trivia(additional_indent_level)
+                ]));
+        let formatted_function_body = formatted_function_body.with_end_token(end_token);
+
+        // We don't know what precedes the anonymous function on its line, so we estimate its width as 20
+        let header_width = (self.indent_level + additional_indent_level.unwrap_or(0))
+            * self.config.indent_width
+            + 20
+            + function_token.to_string().len();
+        let formatted_function_body =
+            self.collapse_function_body(function_body, formatted_function_body, header_width);
 
-        (function_token, function_body.with_end_token(end_token))
+        (function_token, formatted_function_body)
     }
 
     /// Formats a Call node
@@ -317,43 +330,49 @@ impl CodeFormatter {
                             .next()
                             .expect("less arguments than expected");
 
-                        let argument_range =
-                            CodeFormatter::get_range_in_expression(argument.value());
-                        let additional_indent_level =
-                            self.get_range_indent_increase(argument_range);
-
-                        let indent_spacing = (self.indent_level
-                            + additional_indent_level.unwrap_or(0))
-                            * self.config.indent_width;
-                        let require_multiline_expression =
-                            trivia_util::can_hang_expression(argument.value())
-                                && indent_spacing
-                                    + formatted_version
-                                        .to_string()
-                                        .lines()
-                                        .next()
-                                        .expect("no lines")
-                                        .len()
-                                    > self.config.column_width;
-
-                        // Unfortunately, we need to format again, taking into account in indent increase
-                        // TODO: Can we fix this? We don't want to have to format twice
-                        let mut formatted_argument = self.format_expression(argument.value());
-
-                        // Hang the expression if necessary
-                        if require_multiline_expression {
-                            formatted_argument = self.hang_expression_no_trailing_newline(
-                                formatted_argument,
-                                additional_indent_level,
-                                None,
-                            );
-                        }
+                        // Keep the original layout of an argument marked with `stylua: ignore`
+                        // Any comment forces the arguments to be multiline, so we only need to handle it here
+                        let formatted_argument = if !self.should_format_node(argument.value()) {
+                            self.add_ignored_node(argument.value());
+                            argument.value().to_owned()
+                        } else {
+                            let argument_range =
+                                CodeFormatter::get_range_in_expression(argument.value());
+                            let additional_indent_level =
+                                self.get_range_indent_increase(argument_range);
+
+                            let indent_spacing = (self.indent_level
+                                + additional_indent_level.unwrap_or(0))
+                                * self.config.indent_width;
+                            let require_multiline_expression =
+                                trivia_util::can_hang_expression(argument.value())
+                                    && indent_spacing
+                                        + formatted_version
+                                            .to_string()
+                                            .lines()
+                                            .next()
+                                            .expect("no lines")
+                                            .len()
+                                        > self.config.column_width;
+
+                            // Unfortunately, we need to format again, taking into account in indent increase
+                            // TODO: Can we fix this? We don't want to have to format twice
+                            let mut formatted_argument = self.format_expression(argument.value());
+
+                            // Hang the expression if necessary
+                            if require_multiline_expression {
+                                formatted_argument = self.hang_expression_no_trailing_newline(
+                                    formatted_argument,
+                                    additional_indent_level,
+                                    None,
+                                );
+                            }
 
-                        // Add the leading indent for the argument
-                        formatted_argument =
+                            // Add the leading indent for the argument
                             formatted_argument.update_leading_trivia(FormatTriviaType::Append(
                                 vec![self.create_indent_trivia(additional_indent_level)],
-                            ));
+                            ))
+                        };
 
                         let punctuation = match argument.punctuation() {
                             Some(punctuation) => {
@@ -649,6 +668,83 @@ impl CodeFormatter {
         function_body
     }
 
+    /// Moves the block of a formatted function declaration's body onto the same line as its parameters,
+    /// if it is simple enough to be collapsed, such as `function foo() return bar end`.
+    /// `header_width` is the width of the declaration before the parameters, including its indentation.
+    fn collapse_function_body<'ast>(
+        &mut self,
+        function_body: &FunctionBody<'ast>,
+        formatted_function_body: FunctionBody<'ast>,
+        header_width: usize,
+    ) -> FunctionBody<'ast> {
+        // The parameters must fit on a single line, without any comments between them and the block
+        let (start_parens, end_parens) = formatted_function_body.parameters_parentheses().tokens();
+        if trivia_util::trivia_contains_newline(start_parens.trailing_trivia())
+            || trivia_util::token_trivia_contains_comments(
+                function_body
+                    .parameters_parentheses()
+                    .tokens()
+                    .1
+                    .trailing_trivia(),
+            )
+            || trivia_util::token_trivia_contains_comments(
+                function_body.end_token().leading_trivia(),
+            )
+        {
+            return formatted_function_body;
+        }
+
+        #[cfg(feature = "luau")]
+        {
+            if let Some(return_type) = function_body.return_type() {
+                if trivia_util::contains_comments(return_type) {
+                    return formatted_function_body;
+                }
+            }
+        }
+
+        // The block has not been formatted yet, so the parameters and return type are on the first line
+        let parameters_width = formatted_function_body
+            .to_string()
+            .lines()
+            .next()
+            .map_or(0, str::len);
+        if !self.should_collapse_block(function_body.block(), header_width + parameters_width) {
+            return formatted_function_body;
+        }
+
+        let end_token =
+            formatted_function_body
+                .end_token()
+                .update_leading_trivia(FormatTriviaType::Replace(vec![Token::new(
+                    TokenType::spaces(1),
+                )]));
+
+        #[cfg(feature = "luau")]
+        {
+            if let Some(return_type) = formatted_function_body.return_type() {
+                let return_type =
+                    return_type.update_trailing_trivia(FormatTriviaType::Replace(vec![
+                        Token::new(TokenType::spaces(1)),
+                    ]));
+                return formatted_function_body
+                    .with_return_type(Some(return_type))
+                    .with_end_token(end_token);
+            }
+        }
+
+        let parameters_parentheses = ContainedSpan::new(
+            start_parens.to_owned(),
+            end_parens.update_trailing_trivia(FormatTriviaType::Replace(vec![Token::new(
+                TokenType::spaces(1),
+            )])),
+        );
+
+        formatted_function_body
+            .with_parameters_parentheses(parameters_parentheses)
+            .with_end_token(end_token)
+    }
+
     /// Formats a FunctionCall node
     pub fn format_function_call<'ast>(
         &mut self,
@@ -744,9 +840,82 @@ impl CodeFormatter {
             formatted_suffixes.push(suffix);
         }
 
+        // Parentheses can only be omitted from the last call, so that `require("a").b` doesn't read as indexing the string
+        if let Some(Suffix::Call(call)) = formatted_suffixes.pop() {
+            formatted_suffixes.push(Suffix::Call(self.omit_call_parentheses(call)));
+        }
+
         FunctionCall::new(formatted_prefix).with_suffixes(formatted_suffixes)
     }
 
+    /// Removes the parentheses around the arguments of a call if there is a single string or table argument,
+    /// depending on the `call_parentheses` setting. The parentheses are kept if there are any comments within them.
+    fn omit_call_parentheses<'ast>(&self, call: Call<'ast>) -> Call<'ast> {
+        let function_args = match &call {
+            Call::AnonymousCall(function_args) => function_args,
+            Call::MethodCall(method_call) => method_call.args(),
+            _ => return call,
+        };
+
+        let (parentheses, arguments) = match function_args {
+            FunctionArgs::Parentheses {
+                parentheses,
+                arguments,
+            } if arguments.len() == 1 => (parentheses, arguments),
+            _ => return call,
+        };
+
+        let (start_parens, end_parens) = parentheses.tokens();
+        let argument = arguments.iter().next().expect("no arguments");
+        if trivia_util::token_contains_comments(start_parens)
+            || trivia_util::token_trivia_contains_comments(end_parens.leading_trivia())
+            || trivia_util::get_expression_leading_trivia(argument)
+                .iter()
+                .chain(trivia_util::get_expression_trailing_trivia(argument).iter())
+                .any(|trivia| {
+                    trivia.token_kind() == TokenKind::SingleLineComment
+                        || trivia.token_kind() == TokenKind::MultiLineComment
+                })
+        {
+            return call;
+        }
+
+        let value = match argument {
+            #[cfg(feature = "luau")]
+            Expression::Value {
+                type_assertion: Some(_),
+                ..
+            } => return call,
+            Expression::Value { value, .. } => value,
+            _ => return call,
+        };
+
+        // The argument is separated from the function by a single space, and takes any comments after the closing parentheses
+        let leading_trivia = FormatTriviaType::Replace(vec![Token::new(TokenType::spaces(1))]);
+        let trailing_trivia =
+            FormatTriviaType::Append(end_parens.trailing_trivia().cloned().collect());
+
+        let function_args = match (self.config.call_parentheses, &**value) {
+            (CallParenType::NoSingleString, Value::String(token_reference))
+            | (CallParenType::None, Value::String(token_reference)) => {
+                FunctionArgs::String(token_reference.update_trivia(leading_trivia, trailing_trivia))
+            }
+            (CallParenType::NoSingleTable, Value::TableConstructor(table_constructor))
+            | (CallParenType::None, Value::TableConstructor(table_constructor)) => {
+                FunctionArgs::TableConstructor(
+                    table_constructor.update_trivia(leading_trivia, trailing_trivia),
+                )
+            }
+            _ => return call,
+        };
+
+        match call {
+            Call::AnonymousCall(_) => Call::AnonymousCall(function_args),
+            Call::MethodCall(method_call) => Call::MethodCall(method_call.with_args(function_args)),
+            other => other,
+        }
+    }
+
     /// Formats a FunctionName node
     pub fn format_function_name<'ast>(
         &mut self,
@@ -800,6 +969,16 @@ impl CodeFormatter {
         let formatted_function_name = self.format_function_name(function_declaration.name());
         let formatted_function_body = self.format_function_body(function_declaration.body(), true);
 
+        let header_width = (self.indent_level + additional_indent_level.unwrap_or(0))
+            * self.config.indent_width
+            + strip_trivia(&function_token).to_string().len()
+            + formatted_function_name.to_string().len();
+        let formatted_function_body = self.collapse_function_body(
+            function_declaration.body(),
+            formatted_function_body,
+            header_width,
+        );
+
         FunctionDeclaration::new(formatted_function_name)
             .with_function_token(function_token)
             .with_body(formatted_function_body)
@@ -823,6 +1002,17 @@ impl CodeFormatter {
         let formatted_name = self.format_token_reference(local_function.name());
         let formatted_function_body = self.format_function_body(local_function.body(), true);
 
+        let header_width = (self.indent_level + additional_indent_level.unwrap_or(0))
+            * self.config.indent_width
+            + strip_trivia(&local_token).to_string().len()
+            + function_token.to_string().len()
+            + formatted_name.to_string().len();
+        let formatted_function_body = self.collapse_function_body(
+            local_function.body(),
+            formatted_function_body,
+            header_width,
+        );
+
         LocalFunction::new(formatted_name)
             .with_local_token(local_token)
             .with_function_token(function_token)
@@ -1,7 +1,7 @@
 use crate::formatters::{
     table_formatter::TableType,
     trivia_formatter::{FormatTriviaType, UpdateLeadingTrivia, UpdateTrailingTrivia},
-    CodeFormatter,
+    trivia_util, CodeFormatter,
 };
 use full_moon::ast::types::{
     CompoundAssignment, CompoundOp, ExportedTypeDeclaration, GenericDeclaration, IndexedTypeInfo,
@@ -11,7 +11,7 @@ use full_moon::ast::{
     punctuated::{Pair, Punctuated},
     span::ContainedSpan,
 };
-use full_moon::tokenizer::{Token, TokenReference, TokenType};
+use full_moon::tokenizer::{Token, TokenKind, TokenReference, TokenType};
 use std::borrow::Cow;
 use std::boxed::Box;
 
@@ -160,7 +160,9 @@ impl CodeFormatter {
                 );
 
                 let mut current_fields = fields.to_owned().into_pairs().peekable();
-                let is_multiline = (braces_range.1 - braces_range.0) > 30; // TODO: Properly determine this arbitrary number, and see if other factors should come into play
+                let is_multiline = (braces_range.1 - braces_range.0) > 30 // TODO: Properly determine this arbitrary number, and see if other factors should come into play
+                    // A field marked with `stylua: ignore` keeps its own lines, so the table must be multiline
+                    || fields.iter().any(|field| !self.should_format_node(field));
                 let table_type = match current_fields.peek() {
                     Some(_) => match is_multiline {
                         true => TableType::MultiLine,
@@ -198,7 +200,25 @@ impl CodeFormatter {
                         false => FormatTriviaType::NoChange,
                     };
 
-                    let formatted_field = self.format_type_field(&field, leading_trivia);
+                    let mut formatted_field = self.format_type_field(&field, leading_trivia);
+                    // A field marked with `stylua: ignore` keeps its own layout, but the whitespace after it belongs
+                    // to the separator, so only its comments are kept, and moved after the separator
+                    let mut trailing_comments = Vec::new();
+                    if !self.should_format_node(&field) {
+                        trailing_comments = trivia_util::type_info_trailing_trivia(field.value())
+                            .into_iter()
+                            .filter(|token| {
+                                token.token_kind() == TokenKind::SingleLineComment
+                                    || token.token_kind() == TokenKind::MultiLineComment
+                            })
+                            .flat_map(|comment| vec![Token::new(TokenType::spaces(1)), comment])
+                            .collect();
+                        formatted_field = formatted_field.with_value(
+                            field
+                                .value()
+                                .update_trailing_trivia(FormatTriviaType::Replace(vec![])),
+                        );
+                    }
                     let mut formatted_punctuation = None;
 
                     match is_multiline {
@@ -209,6 +229,7 @@ impl CodeFormatter {
                                 Some(punctuation) => crate::fmt_symbol!(self, &punctuation, ","),
                                 None => TokenReference::symbol(",").unwrap(),
                             }
+                            .update_trailing_trivia(FormatTriviaType::Append(trailing_comments))
                             .update_trailing_trivia(
                                 FormatTriviaType::Append(vec![self.create_newline_trivia()]),
                             );
@@ -316,6 +337,8 @@ impl CodeFormatter {
         type_field: &TypeField<'ast>,
         leading_trivia: FormatTriviaType<'ast>,
     ) -> TypeField<'ast> {
+        crate::check_should_format!(self, type_field);
+
         let key = self.format_type_field_key(type_field.key(), leading_trivia);
         let colon_token = crate::fmt_symbol!(self, type_field.colon_token(), ": ");
         let value = self.format_type_info(type_field.value());
@@ -1,14 +1,15 @@
-use crate::{Config, IndentType, LineEndings, QuoteStyle};
+use crate::{CommentStyle, Config, IndentType, LineEndings, NumberCase, QuoteStyle};
 use full_moon::ast::{
     punctuated::{Pair, Punctuated},
     span::ContainedSpan,
-    Block,
+    Block, Expression, Value,
 };
 use full_moon::node::Node;
 use full_moon::tokenizer::{StringLiteralQuoteType, Token, TokenKind, TokenReference, TokenType};
 use full_moon::visitors::VisitorMut;
 use std::borrow::Cow;
 use std::collections::HashSet;
+use trivia_formatter::{FormatTriviaType, UpdateLeadingTrivia};
 
 pub mod assignment_formatter;
 pub mod block_formatter;
@@ -37,6 +38,48 @@ pub struct CodeFormatter {
     indent_level: usize,
     /// A link of specific ranges to indent increases. The indent increases are added ontop of indent_level
     indent_ranges: HashSet<Range>,
+    /// The ranges of any nodes skipped due to an ignore comment. Blocks within these ranges are left untouched
+    ignored_ranges: Vec<Range>,
+    /// The ranges of blocks which have been collapsed onto the same line as the statement containing them
+    collapsed_blocks: HashSet<Range>,
+    /// The ranges of the top-level statements, in order. Trivia outside of these is between top-level statements
+    top_level_statements: Vec<Range>,
+}
+
+/// A comment controlling which parts of the code are formatted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IgnoreDirective {
+    /// `stylua: ignore`, skipping the node the comment is attached to
+    Ignore,
+    /// `stylua: ignore start`, skipping every statement until the end of the region or the enclosing block
+    Start,
+    /// `stylua: ignore end`, ending a region started with `stylua: ignore start`
+    End,
+}
+
+/// Returns any ignore directives found in the given trivia, in the order they are written
+pub fn ignore_directives<'a, 'ast: 'a>(
+    trivia: impl IntoIterator<Item = &'a Token<'ast>>,
+) -> Vec<IgnoreDirective> {
+    let mut directives = Vec::new();
+    for trivia in trivia {
+        let comment = match trivia.token_type() {
+            TokenType::SingleLineComment { comment } => comment,
+            TokenType::MultiLineComment { comment, .. } => comment,
+            _ => continue,
+        };
+
+        for line in comment.lines() {
+            match line.trim() {
+                "stylua: ignore" => directives.push(IgnoreDirective::Ignore),
+                "stylua: ignore start" => directives.push(IgnoreDirective::Start),
+                "stylua: ignore end" => directives.push(IgnoreDirective::End),
+                _ => (),
+            }
+        }
+    }
+
+    directives
 }
 
 #[derive(Debug)]
@@ -65,6 +108,90 @@ fn get_line_ending_character(line_endings: &LineEndings) -> String {
     }
 }
 
+/// Changes the case of any letters in the text, as part of a number literal
+fn change_case(text: &str, case: NumberCase) -> String {
+    match case {
+        NumberCase::Preserve => text.to_owned(),
+        NumberCase::Lower => text.to_ascii_lowercase(),
+        NumberCase::Upper => text.to_ascii_uppercase(),
+    }
+}
+
+/// Determines whether the text of a comment looks like commented-out code, rather than prose
+fn is_commented_out_code(comment: &str) -> bool {
+    let comment = comment.trim();
+
+    // Lines which open or close a block, such as `if x then` or `end)`, cannot be parsed on their own
+    let first_word = comment.split_whitespace().next().unwrap_or_default();
+    let last_word = comment.split_whitespace().last().unwrap_or_default();
+    if comment.ends_with(['{', '('])
+        || comment.starts_with(['}', ')'])
+        || ["end", "else", "elseif", "until"].contains(&first_word)
+        || ["then", "do", "else", "end", "repeat"].contains(&last_word)
+    {
+        return true;
+    }
+
+    match full_moon::parse(comment) {
+        Ok(ast) => ast.nodes().stmts().next().is_some() || ast.nodes().last_stmt().is_some(),
+        Err(_) => false,
+    }
+}
+
+/// The keywords which cannot be used as identifiers.
+/// full_moon always tokenizes `goto` as a keyword, even when Lua 5.2 support is not enabled
+const KEYWORDS: [&str; 22] = [
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/// Determines whether a string can be written as an identifier, such as a table key or a `.` index
+pub fn is_valid_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    let starts_with_letter = chars
+        .next()
+        .filter(|c| c.is_ascii_alphabetic() || *c == '_')
+        .is_some();
+
+    starts_with_letter
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name)
+}
+
+/// Returns the contents of an expression which is only a quoted string literal, such as the key in `["name"] = value`
+pub fn string_literal_value<'a>(expression: &'a Expression) -> Option<&'a str> {
+    match expression {
+        #[cfg(feature = "luau")]
+        Expression::Value {
+            type_assertion: Some(_),
+            ..
+        } => None,
+        Expression::Value { value, .. } => match &**value {
+            Value::String(token) => match token.token_type() {
+                TokenType::StringLiteral {
+                    literal,
+                    quote_type,
+                    ..
+                } if *quote_type != StringLiteralQuoteType::Brackets => Some(literal),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Adds spaces before an `=` token, to line it up with the `=` of the surrounding assignments or fields
+pub fn pad_equal_token<'ast>(equal: &TokenReference<'ast>, padding: usize) -> TokenReference<'ast> {
+    if padding == 0 {
+        return equal.to_owned();
+    }
+
+    equal.update_leading_trivia(FormatTriviaType::Append(vec![Token::new(
+        TokenType::spaces(padding),
+    )]))
+}
+
 #[macro_export]
 macro_rules! fmt_symbol {
     ($fmter:expr, $token:expr, $x:expr) => {
@@ -89,6 +216,9 @@ impl CodeFormatter {
             config,
             range,
             indent_ranges: HashSet::new(),
+            ignored_ranges: Vec::new(),
+            collapsed_blocks: HashSet::new(),
+            top_level_statements: Vec::new(),
         }
     }
 
@@ -98,21 +228,8 @@ impl CodeFormatter {
     /// If not, the node should not be formatted.
     pub fn should_format_node<'ast>(&self, node: &impl Node<'ast>) -> bool {
         // Check comments
-        let leading_trivia = node.surrounding_trivia().0;
-        for trivia in leading_trivia {
-            let comment_lines = match trivia.token_type() {
-                TokenType::SingleLineComment { comment } => comment,
-                TokenType::MultiLineComment { comment, .. } => comment,
-                _ => continue,
-            }
-            .lines()
-            .map(|line| line.trim());
-
-            for line in comment_lines {
-                if line == "stylua: ignore" {
-                    return false;
-                }
-            }
+        if ignore_directives(node.surrounding_trivia().0).contains(&IgnoreDirective::Ignore) {
+            return false;
         }
 
         if let Some(range) = self.range {
@@ -141,6 +258,26 @@ impl CodeFormatter {
         }
     }
 
+    /// Records that the given node was skipped due to an ignore comment, so that any blocks within it are not formatted
+    pub fn add_ignored_node<'ast>(&mut self, node: &impl Node<'ast>) {
+        if let Some((start, end)) = node.range() {
+            self.ignored_ranges.push((start.bytes(), end.bytes()));
+        }
+    }
+
+    /// Whether the given node lies within a node which was skipped due to an ignore comment
+    fn is_within_ignored_node<'ast>(&self, node: &impl Node<'ast>) -> bool {
+        match node.range() {
+            Some((start, end)) => self
+                .ignored_ranges
+                .iter()
+                .any(|(ignored_start, ignored_end)| {
+                    *ignored_start <= start.bytes() && end.bytes() <= *ignored_end
+                }),
+            None => false,
+        }
+    }
+
     /// Increase the level of indention at the current position of the formatter
     pub fn increment_indent_level(&mut self) {
         self.indent_level += 1;
@@ -199,6 +336,79 @@ impl CodeFormatter {
         }
     }
 
+    /// Whether the trivia at the given byte position is between top-level statements, or at the end of the file
+    fn is_top_level_trivia(&self, position: usize) -> bool {
+        let index = self
+            .top_level_statements
+            .partition_point(|(start, _)| *start < position);
+        self.indent_level <= 1
+            && match index.checked_sub(1) {
+                Some(index) => self.top_level_statements[index].1 <= position,
+                None => true,
+            }
+    }
+
+    /// The maximum number of consecutive blank lines to keep in the trivia at the given byte position
+    fn max_blank_lines(&self, position: usize) -> usize {
+        match self.config.max_top_level_blank_lines {
+            Some(max_blank_lines) if self.is_top_level_trivia(position) => max_blank_lines,
+            _ => self.config.max_blank_lines,
+        }
+    }
+
+    /// Determines how many spaces to add before the `=` of each item in a list of assignments or table fields,
+    /// so that the equals signs of consecutive items line up when `align_assignments` is enabled.
+    /// Each item is the width of the text before its `=`, the width of the line containing it, and whether a blank line
+    /// comes before it, or `None` if it cannot be aligned.
+    /// Runs of items end at blank lines and unaligned items, and are split wherever the maximum padding would be exceeded.
+    /// A run is left unaligned if the padding would push any of its lines past the column width
+    pub fn alignment_padding(&self, items: &[Option<(usize, usize, bool)>]) -> Vec<usize> {
+        let mut padding = vec![0; items.len()];
+        if !self.config.align_assignments {
+            return padding;
+        }
+
+        // Each run is a list of item indices alongside their widths
+        let mut runs = Vec::new();
+        let mut run: Vec<(usize, usize, usize)> = Vec::new();
+        for (index, item) in items.iter().enumerate() {
+            let (width, line_width, after_blank_line) = match item {
+                Some(item) => *item,
+                None => {
+                    runs.push(std::mem::take(&mut run));
+                    continue;
+                }
+            };
+
+            let widths = run
+                .iter()
+                .map(|(_, width, _)| *width)
+                .chain(std::iter::once(width));
+            let spread = widths.clone().max().unwrap_or(0) - widths.min().unwrap_or(0);
+            if after_blank_line || spread > self.config.align_assignments_max_padding {
+                runs.push(std::mem::take(&mut run));
+            }
+            run.push((index, width, line_width));
+        }
+        runs.push(run);
+
+        for run in runs {
+            let max_width = run.iter().map(|(_, width, _)| *width).max().unwrap_or(0);
+            let overflows = run.iter().any(|(_, width, line_width)| {
+                max_width > *width && line_width + max_width - width > self.config.column_width
+            });
+            if overflows {
+                continue;
+            }
+
+            for (index, width, _) in run {
+                padding[index] = max_width - width;
+            }
+        }
+
+        padding
+    }
+
     /// Creates a new Token containing new line whitespace, used for trivia
     pub fn create_newline_trivia<'ast>(&self) -> Token<'ast> {
         Token::new(TokenType::Whitespace {
@@ -206,9 +416,227 @@ impl CodeFormatter {
         })
     }
 
-    fn format_single_line_comment_string(&self, comment: String) -> String {
+    /// Normalizes a number literal. Every change keeps the value of the number the same
+    fn format_number_literal(&self, text: &str) -> String {
+        // Add a leading zero to numbers such as `.5`
+        let text = if text.starts_with('.') {
+            String::from("0") + text
+        } else if text.starts_with("-.") {
+            String::from("-0") + text.get(1..).expect("unknown number literal")
+        } else {
+            text.to_owned()
+        };
+
+        if let Some(digits) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            let prefix = match self.config.hex_prefix_case {
+                NumberCase::Preserve => &text[..2],
+                NumberCase::Lower => "0x",
+                NumberCase::Upper => "0X",
+            };
+            return prefix.to_owned() + &change_case(digits, self.config.hex_digit_case);
+        }
+
+        // Leave any other prefixed literals, such as Luau's binary literals, as they are
+        if text
+            .chars()
+            .nth(1)
+            .filter(|c| c.is_ascii_alphabetic() && !c.eq_ignore_ascii_case(&'e'))
+            .is_some()
+        {
+            return text;
+        }
+
+        let (mantissa, exponent) = match text.find(['e', 'E']) {
+            Some(index) => (
+                &text[..index],
+                Some((&text[index..=index], &text[index + 1..])),
+            ),
+            None => (text.as_str(), None),
+        };
+
+        let mut formatted = mantissa.to_owned();
+        if self.config.normalize_trailing_dot && mantissa.ends_with('.') {
+            formatted.push('0');
+        }
+
+        if let Some((exponent_letter, exponent)) = exponent {
+            formatted += &change_case(exponent_letter, self.config.exponent_case);
+            formatted += match exponent.strip_prefix('+') {
+                Some(exponent) if self.config.remove_exponent_plus => exponent,
+                _ => exponent,
+            };
+        }
+
+        formatted
+    }
+
+    /// Determines whether a single-line comment should be normalized according to the comment style.
+    /// Doc comments, directives and commented-out code are always left as they are.
+    /// `indent_width` is the width of the comment's indentation, if it is on its own line and so can be wrapped
+    fn should_normalize_comment(&self, comment: &str, indent_width: Option<usize>) -> bool {
+        if self.config.comment_style != CommentStyle::Normalize
+            || comment.starts_with(['-', '!'])
+            || comment.trim().is_empty()
+            || comment.trim_start().starts_with("stylua:")
+        {
+            return false;
+        }
+
+        // Checking for commented-out code parses the comment, so avoid it when normalizing would not change anything
+        let comment = comment.trim_end();
+        let is_spaced = comment.starts_with(' ') && !comment[1..].starts_with(char::is_whitespace);
+        let fits = match indent_width {
+            Some(indent_width) => {
+                indent_width + "--".len() + comment.chars().count() <= self.config.column_width
+            }
+            None => true,
+        };
+        if is_spaced && fits {
+            return false;
+        }
+
+        !is_commented_out_code(comment)
+    }
+
+    fn format_single_line_comment_string(&self, comment: String, normalize: bool) -> String {
         // Trim any trailing whitespace
-        comment.trim_end().to_string()
+        let comment = comment.trim_end();
+
+        if normalize {
+            // Use a single space after the `--`
+            format!(" {}", comment.trim_start())
+        } else {
+            comment.to_string()
+        }
+    }
+
+    /// Splits a comment which does not fit within the column width over multiple lines, breaking it between words.
+    /// Each line returned is the text of a comment following the `--`
+    fn wrap_single_line_comment(&self, comment: &str, indent_width: usize) -> Vec<String> {
+        if indent_width + "--".len() + comment.chars().count() <= self.config.column_width {
+            return vec![comment.to_owned()];
+        }
+
+        let max_width = self
+            .config
+            .column_width
+            .saturating_sub(indent_width + "-- ".len());
+        let mut lines = Vec::new();
+        let mut line = String::new();
+
+        // Words on the same line keep the whitespace between them, which is only removed where the comment is broken
+        let mut rest = comment.trim_start();
+        let mut separator = "";
+        while !rest.is_empty() {
+            let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let word = &rest[..word_end];
+            if !line.is_empty()
+                && line.chars().count() + separator.chars().count() + word.chars().count()
+                    > max_width
+            {
+                lines.push(format!(" {}", line));
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push_str(separator);
+            }
+            line.push_str(word);
+
+            let after_word = &rest[word_end..];
+            rest = after_word.trim_start();
+            separator = &after_word[..after_word.len() - rest.len()];
+        }
+        lines.push(format!(" {}", line));
+
+        lines
+    }
+
+    /// Removes any trailing whitespace from the lines of a multi-line comment.
+    /// The last line is left alone, as it is followed by the closing brackets.
+    fn format_multi_line_comment_string(&self, comment: &str) -> String {
+        let mut lines: Vec<&str> = comment.split('\n').collect();
+        let last_line = lines.pop().unwrap_or_default();
+
+        lines
+            .iter()
+            .map(|line| {
+                // Keep the line endings within the comment as they are
+                let carriage_return = if line.ends_with('\r') { "\r" } else { "" };
+                line.trim_end().to_owned() + carriage_return
+            })
+            .chain(std::iter::once(last_line.to_owned()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Returns the width of the line on which a formatted node starts, including its indentation.
+    /// `leading_trivia` is the leading trivia of the node, which may include comments on the lines before it
+    fn first_line_width(&self, text: &str, leading_trivia: &[&Token]) -> usize {
+        let leading_trivia: String = leading_trivia
+            .iter()
+            .map(|token| token.to_string())
+            .collect();
+        let line_start = leading_trivia.rfind('\n').map_or(0, |index| index + 1);
+        let line = text[line_start..].lines().next().unwrap_or_default();
+        let code = line.trim_start();
+        self.indent_string_width(&line[..line.len() - code.len()]) + code.chars().count()
+    }
+
+    /// Returns the width of some indentation, where each tab is the width of a single indent
+    fn indent_string_width(&self, indent: &str) -> usize {
+        indent
+            .chars()
+            .map(|c| {
+                if c == '\t' {
+                    self.config.indent_width
+                } else {
+                    1
+                }
+            })
+            .sum()
+    }
+
+    /// Shifts the lines after the first in a multi-line comment by the change in indentation of the line the comment starts on,
+    /// so that the comment keeps its shape when it is moved to a different indent level
+    fn reindent_multi_line_comment(
+        &self,
+        comment: &str,
+        original_indent: &str,
+        indent: &str,
+    ) -> String {
+        let lines: Vec<&str> = comment.split('\n').collect();
+        let last_index = lines.len() - 1;
+
+        lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                let content = line.trim_start();
+                // Blank lines are left empty, but the last line is the indentation of the closing brackets
+                if index == 0 || (index != last_index && content.trim_end().is_empty()) {
+                    return (*line).to_owned();
+                }
+
+                match line.strip_prefix(original_indent) {
+                    Some(rest) => indent.to_owned() + rest,
+                    None => {
+                        // The line is indented differently to the start of the comment, so shift it by the difference in width
+                        let width = (self.indent_string_width(&line[..line.len() - content.len()])
+                            + self.indent_string_width(indent))
+                        .saturating_sub(self.indent_string_width(original_indent));
+                        let indent = match self.config.indent_type {
+                            IndentType::Tabs => {
+                                "\t".repeat(width / self.config.indent_width)
+                                    + &" ".repeat(width % self.config.indent_width)
+                            }
+                            IndentType::Spaces => " ".repeat(width),
+                        };
+                        indent + content
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
     }
 
     fn get_quote_to_use(&self, literal: &str) -> StringLiteralQuoteType {
@@ -257,17 +685,7 @@ impl CodeFormatter {
 
         let token_type = match token.token_type() {
             TokenType::Number { text } => TokenType::Number {
-                text: Cow::Owned(if text.starts_with('.') {
-                    String::from("0")
-                        + match text {
-                            Cow::Owned(text) => text.as_str(),
-                            Cow::Borrowed(text) => text,
-                        }
-                } else if text.starts_with("-.") {
-                    String::from("-0") + text.get(1..).expect("unknown number literal")
-                } else {
-                    text.to_owned().into_owned()
-                }),
+                text: Cow::Owned(self.format_number_literal(text)),
             },
             TokenType::StringLiteral {
                 literal,
@@ -343,10 +761,8 @@ impl CodeFormatter {
                 }
             }
             TokenType::SingleLineComment { comment } => {
-                let comment =
-                    self.format_single_line_comment_string(comment.to_owned().into_owned());
-
-                match format_type {
+                // Comments on their own line are indented, and wrapped if they are too long
+                let indent = match format_type {
                     FormatTokenType::LeadingTrivia => {
                         let additional_indent_level = additional_indent_level.unwrap_or(0)
                             + self
@@ -355,12 +771,43 @@ impl CodeFormatter {
                                     token.end_position().bytes(),
                                 ))
                                 .unwrap_or(0);
-                        leading_trivia = Some(vec![
-                            self.create_indent_trivia(Some(additional_indent_level))
-                        ]);
-                        trailing_trivia = Some(vec![self.create_newline_trivia()]);
+                        Some(self.create_indent_trivia(Some(additional_indent_level)))
                     }
-                    FormatTokenType::TrailingTrivia => {
+                    _ => None,
+                };
+                let indent_width = indent
+                    .as_ref()
+                    .map(|indent| self.indent_string_width(&indent.to_string()));
+
+                let normalize = self.should_normalize_comment(comment, indent_width);
+                let mut comment = self
+                    .format_single_line_comment_string(comment.to_owned().into_owned(), normalize);
+
+                match (format_type, indent, indent_width) {
+                    (FormatTokenType::LeadingTrivia, Some(indent), Some(indent_width)) => {
+                        let mut trivia = Vec::new();
+
+                        // Comments which are too long are continued on the following lines, at the same indent
+                        if normalize {
+                            let mut lines = self
+                                .wrap_single_line_comment(&comment, indent_width)
+                                .into_iter();
+                            comment = lines.next().unwrap_or_default();
+
+                            for line in lines {
+                                trivia.push(self.create_newline_trivia());
+                                trivia.push(indent.to_owned());
+                                trivia.push(Token::new(TokenType::SingleLineComment {
+                                    comment: Cow::Owned(line),
+                                }));
+                            }
+                        }
+                        trivia.push(self.create_newline_trivia());
+
+                        leading_trivia = Some(vec![indent]);
+                        trailing_trivia = Some(trivia);
+                    }
+                    (FormatTokenType::TrailingTrivia, _, _) => {
                         // Add a space before the comment
                         leading_trivia = Some(vec![Token::new(TokenType::spaces(1))]);
                     }
@@ -372,8 +819,11 @@ impl CodeFormatter {
                 }
             }
             TokenType::MultiLineComment { blocks, comment } => {
-                // let comment =
-                //     self.format_multi_line_comment_string(comment.to_owned().into_owned());
+                let comment = if self.config.format_multiline_comments {
+                    Cow::Owned(self.format_multi_line_comment_string(comment))
+                } else {
+                    comment.to_owned()
+                };
 
                 if let FormatTokenType::LeadingTrivia = format_type {
                     let additional_indent_level = additional_indent_level.unwrap_or(0)
@@ -392,7 +842,7 @@ impl CodeFormatter {
 
                 TokenType::MultiLineComment {
                     blocks: *blocks,
-                    comment: comment.to_owned(),
+                    comment,
                 }
             }
             TokenType::Whitespace { characters } => TokenType::Whitespace {
@@ -417,21 +867,30 @@ impl CodeFormatter {
         let mut token_trivia = Vec::new();
 
         let mut newline_count_in_succession = 0;
+        // The whitespace before the current trivia on the same line, which is its original indentation
+        let mut line_indent = "";
         let mut trivia_iter = current_trivia.iter().peekable();
 
         while let Some(trivia) = trivia_iter.next() {
             // Handle cases where the user has left a newline gap in between e.g. two statements
             // If we are formatting trailing trivia, this can be ignored, as all trailing newlines will have already
             // been handled by the formatter.
-            // If we are formatting leading trivia, we will allow a single newline to be kept in succession, if we
-            // find one.
+            // If we are formatting leading trivia, we will allow up to the maximum number of blank lines to be
+            // kept in succession, if we find any.
             match trivia.token_type() {
                 TokenType::Whitespace { characters } => {
+                    line_indent = match characters.rfind('\n') {
+                        Some(index) => &characters[index + 1..],
+                        None => characters,
+                    };
+
                     if let FormatTokenType::LeadingTrivia = format_token_type {
                         if characters.contains('\n') {
                             newline_count_in_succession += 1;
-                            if newline_count_in_succession == 1 {
-                                // We have a case where we will allow a single newline to be kept
+                            if newline_count_in_succession
+                                <= self.max_blank_lines(trivia.start_position().bytes())
+                            {
+                                // We have a case where we will allow the newline to be kept
                                 token_trivia.push(self.create_newline_trivia());
                             }
                         }
@@ -462,11 +921,32 @@ impl CodeFormatter {
                 }
             }
 
-            let (token, leading_trivia, trailing_trivia) = self.format_token(
+            let (mut token, leading_trivia, trailing_trivia) = self.format_token(
                 trivia.to_owned(),
                 &format_token_type,
                 additional_indent_level,
             );
+
+            // Multi-line comments placed at a new indent level have the rest of their lines moved with them
+            if let (TokenType::MultiLineComment { blocks, comment }, Some(indent)) =
+                (token.token_type(), &leading_trivia)
+            {
+                if self.config.format_multiline_comments
+                    && matches!(format_token_type, FormatTokenType::LeadingTrivia)
+                {
+                    let indent: String = indent.iter().map(Token::to_string).collect();
+                    token = Token::new(TokenType::MultiLineComment {
+                        blocks: *blocks,
+                        comment: Cow::Owned(self.reindent_multi_line_comment(
+                            comment,
+                            line_indent,
+                            &indent,
+                        )),
+                    });
+                }
+            }
+            line_indent = "";
+
             if let Some(mut trivia) = leading_trivia {
                 token_trivia.append(&mut trivia);
             }
@@ -710,7 +1190,31 @@ fn pop_until_no_whitespace(trivia: &mut Vec<Token>) {
 impl<'ast> VisitorMut<'ast> for CodeFormatter {
     fn visit_block(&mut self, node: Block<'ast>) -> Block<'ast> {
         self.increment_indent_level();
-        self.format_block(node)
+        if self.indent_level == 1 {
+            self.top_level_statements = node
+                .stmts()
+                .map(Node::range)
+                .chain(node.last_stmt().map(Node::range))
+                .flatten()
+                .map(|(start, end)| (start.bytes(), end.bytes()))
+                .collect();
+        }
+        if self.is_within_ignored_node(&node) {
+            return node;
+        }
+
+        let collapsed = match node.range() {
+            Some((start, end)) => self
+                .collapsed_blocks
+                .contains(&(start.bytes(), end.bytes())),
+            None => false,
+        };
+        let block = self.format_block(node);
+        if collapsed {
+            CodeFormatter::collapse_block(block)
+        } else {
+            block
+        }
     }
 
     fn visit_block_end(&mut self, node: Block<'ast>) -> Block<'ast> {
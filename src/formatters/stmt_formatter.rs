@@ -22,7 +22,7 @@ macro_rules! fmt_stmt {
 
 impl CodeFormatter {
     /// Format a Do node
-    pub fn format_do_block<'ast>(&self, do_block: &Do<'ast>) -> Do<'ast> {
+    pub fn format_do_block<'ast>(&mut self, do_block: &Do<'ast>) -> Do<'ast> {
         // Create trivia
         let additional_indent_level =
             self.get_range_indent_increase(CodeFormatter::get_token_range(do_block.do_token()));
@@ -30,11 +30,29 @@ impl CodeFormatter {
             FormatTriviaType::Append(vec![self.create_indent_trivia(additional_indent_level)]);
         let trailing_trivia = FormatTriviaType::Append(vec![self.create_newline_trivia()]);
 
+        // A simple block, such as `do return end`, can be kept on a single line
+        let indent_spacing =
+            (self.indent_level + additional_indent_level.unwrap_or(0)) * self.config.indent_width;
+        let collapse_block =
+            !trivia_util::token_trivia_contains_comments(do_block.do_token().trailing_trivia())
+                && !trivia_util::token_trivia_contains_comments(
+                    do_block.end_token().leading_trivia(),
+                )
+                && self.should_collapse_block(do_block.block(), indent_spacing + "do".len());
+        let (do_trailing_trivia, end_leading_trivia) = if collapse_block {
+            (
+                FormatTriviaType::Append(vec![Token::new(TokenType::spaces(1))]),
+                FormatTriviaType::Append(vec![Token::new(TokenType::spaces(1))]),
+            )
+        } else {
+            (trailing_trivia.to_owned(), leading_trivia.to_owned())
+        };
+
         let do_token = crate::fmt_symbol!(self, do_block.do_token(), "do")
-            .update_trivia(leading_trivia.to_owned(), trailing_trivia.to_owned());
+            .update_trivia(leading_trivia, do_trailing_trivia);
         let end_token = self
             .format_end_token(do_block.end_token(), EndTokenType::BlockEnd)
-            .update_trivia(leading_trivia, trailing_trivia);
+            .update_trivia(end_leading_trivia, trailing_trivia);
 
         do_block
             .to_owned()
@@ -210,6 +228,22 @@ impl CodeFormatter {
             self.format_expression(if_node.condition())
         };
 
+        // A simple block, such as `if not x then return end`, can be kept on a single line
+        let collapse_block = !require_multiline_expression
+            && if_node.else_if().is_none()
+            && if_node.else_token().is_none()
+            && !trivia_util::token_contains_comments(if_node.then_token())
+            && !trivia_util::token_trivia_contains_comments(if_node.end_token().leading_trivia())
+            && self.should_collapse_block(if_node.block(), indent_spacing + last_line_str_len);
+        let (then_trailing_trivia, end_leading_trivia) = if collapse_block {
+            (
+                vec![Token::new(TokenType::spaces(1))],
+                vec![Token::new(TokenType::spaces(1))],
+            )
+        } else {
+            (trailing_trivia.to_owned(), leading_trivia.to_owned())
+        };
+
         let formatted_then_token = crate::fmt_symbol!(self, if_node.then_token(), then_text)
             .update_trivia(
                 if require_multiline_expression {
@@ -217,12 +251,12 @@ impl CodeFormatter {
                 } else {
                     FormatTriviaType::NoChange
                 },
-                FormatTriviaType::Append(trailing_trivia.to_owned()),
+                FormatTriviaType::Append(then_trailing_trivia),
             );
         let formatted_end_token = self
             .format_end_token(if_node.end_token(), EndTokenType::BlockEnd)
             .update_trivia(
-                FormatTriviaType::Append(leading_trivia.to_owned()),
+                FormatTriviaType::Append(end_leading_trivia),
                 FormatTriviaType::Append(trailing_trivia.to_owned()),
             );
 
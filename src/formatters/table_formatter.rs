@@ -1,13 +1,18 @@
-use crate::formatters::{
-    trivia_formatter::{FormatTriviaType, UpdateLeadingTrivia, UpdateTrailingTrivia},
-    trivia_util, CodeFormatter, EndTokenType,
+use crate::{
+    formatters::{
+        is_valid_identifier, pad_equal_token, string_literal_value,
+        trivia_formatter::{FormatTriviaType, UpdateLeadingTrivia, UpdateTrailingTrivia},
+        trivia_util, CodeFormatter, EndTokenType,
+    },
+    KeyStyle,
 };
 use full_moon::ast::{
     punctuated::{Pair, Punctuated},
     span::ContainedSpan,
-    Field, TableConstructor,
+    Expression, Field, TableConstructor, Value,
 };
-use full_moon::tokenizer::{Token, TokenKind, TokenReference};
+use full_moon::tokenizer::{Symbol, Token, TokenKind, TokenReference, TokenType};
+use std::borrow::Cow;
 
 /// Used to provide information about the table
 #[derive(Debug, Clone, Copy)]
@@ -26,6 +31,46 @@ impl CodeFormatter {
         field: &Field<'ast>,
         leading_trivia: FormatTriviaType<'ast>,
     ) -> (Field<'ast>, Vec<Token<'ast>>) {
+        // Keep the original layout of a field marked with `stylua: ignore`.
+        // The whitespace after its value belongs to the separator, so only any comments are kept and moved after it
+        if !self.should_format_node(field) {
+            self.add_ignored_node(field);
+            return match field {
+                Field::ExpressionKey {
+                    brackets,
+                    key,
+                    equal,
+                    value,
+                } => {
+                    let (value, trailing_comments) =
+                        trivia_util::get_expression_trailing_comments(value);
+                    let field = Field::ExpressionKey {
+                        brackets: brackets.to_owned(),
+                        key: key.to_owned(),
+                        equal: equal.to_owned(),
+                        value,
+                    };
+                    (field, trailing_comments)
+                }
+                Field::NameKey { key, equal, value } => {
+                    let (value, trailing_comments) =
+                        trivia_util::get_expression_trailing_comments(value);
+                    let field = Field::NameKey {
+                        key: key.to_owned(),
+                        equal: equal.to_owned(),
+                        value,
+                    };
+                    (field, trailing_comments)
+                }
+                Field::NoKey(expression) => {
+                    let (expression, trailing_comments) =
+                        trivia_util::get_expression_trailing_comments(expression);
+                    (Field::NoKey(expression), trailing_comments)
+                }
+                other => panic!("unknown node {:?}", other),
+            };
+        }
+
         let trailing_trivia;
         let field = match field {
             Field::ExpressionKey {
@@ -79,6 +124,135 @@ impl CodeFormatter {
         (field, trailing_trivia)
     }
 
+    /// Pads the `=` of runs of consecutive `name = value` fields in a multi-line table, so that their equals signs line up
+    fn align_table_fields<'ast>(
+        &self,
+        fields: Punctuated<'ast, Field<'ast>>,
+        alignable: &[bool],
+    ) -> Punctuated<'ast, Field<'ast>> {
+        let items: Vec<_> = fields
+            .iter()
+            .zip(alignable)
+            .map(|(field, alignable)| match (field, alignable) {
+                (Field::NameKey { key, .. }, true) => {
+                    let leading_trivia = key.leading_trivia().collect::<Vec<_>>();
+                    // The line also contains the `,` after the field
+                    let line_width =
+                        self.first_line_width(&field.to_string(), &leading_trivia) + ",".len();
+                    let after_blank_line = trivia_util::trivia_contains_blank_line(&leading_trivia);
+                    Some((
+                        key.token().to_string().chars().count(),
+                        line_width,
+                        after_blank_line,
+                    ))
+                }
+                _ => None,
+            })
+            .collect();
+        let padding = self.alignment_padding(&items);
+
+        fields
+            .into_pairs()
+            .zip(padding)
+            .map(|(pair, padding)| {
+                pair.map(|field| match field {
+                    Field::NameKey { key, equal, value } => Field::NameKey {
+                        key,
+                        equal: pad_equal_token(&equal, padding),
+                        value,
+                    },
+                    other => other,
+                })
+            })
+            .collect()
+    }
+
+    /// Rewrites the key of a formatted field according to the key style, between `["name"] = value` and `name = value`.
+    /// Keys are written with brackets when `use_brackets` is set, so that they are consistent with the rest of the table
+    fn format_field_key<'ast>(&self, field: Field<'ast>, use_brackets: bool) -> Field<'ast> {
+        if self.config.key_style == KeyStyle::Preserve {
+            return field;
+        }
+
+        match field {
+            Field::ExpressionKey {
+                brackets,
+                key,
+                equal,
+                value,
+            } if !use_brackets => {
+                let name = string_literal_value(&key)
+                    .filter(|name| is_valid_identifier(name))
+                    .map(str::to_owned);
+
+                match name {
+                    // Keep the brackets if removing them would lose any comments
+                    Some(name)
+                        if !trivia_util::contains_comments(&brackets)
+                            && !trivia_util::contains_comments(&key) =>
+                    {
+                        Field::NameKey {
+                            key: TokenReference::new(
+                                brackets.tokens().0.leading_trivia().cloned().collect(),
+                                Token::new(TokenType::Identifier {
+                                    identifier: Cow::Owned(name),
+                                }),
+                                vec![],
+                            ),
+                            equal,
+                            value,
+                        }
+                    }
+                    _ => Field::ExpressionKey {
+                        brackets,
+                        key,
+                        equal,
+                        value,
+                    },
+                }
+            }
+            Field::NameKey { key, equal, value } if use_brackets => {
+                let name = key.token().to_string();
+                let quote_type = self.get_quote_to_use(&name);
+
+                Field::ExpressionKey {
+                    brackets: ContainedSpan::new(
+                        TokenReference::new(
+                            key.leading_trivia().cloned().collect(),
+                            Token::new(TokenType::Symbol {
+                                symbol: Symbol::LeftBracket,
+                            }),
+                            vec![],
+                        ),
+                        TokenReference::new(
+                            vec![],
+                            Token::new(TokenType::Symbol {
+                                symbol: Symbol::RightBracket,
+                            }),
+                            key.trailing_trivia().cloned().collect(),
+                        ),
+                    ),
+                    key: Expression::Value {
+                        value: Box::new(Value::String(TokenReference::new(
+                            vec![],
+                            Token::new(TokenType::StringLiteral {
+                                literal: Cow::Owned(name),
+                                multi_line: None,
+                                quote_type,
+                            }),
+                            vec![],
+                        ))),
+                        #[cfg(feature = "luau")]
+                        type_assertion: None,
+                    },
+                    equal,
+                    value,
+                }
+            }
+            other => other,
+        }
+    }
+
     pub fn create_table_braces<'ast>(
         &self,
         start_brace: &TokenReference<'ast>,
@@ -154,10 +328,31 @@ impl CodeFormatter {
             end_brace.token().start_position().bytes(),
         );
 
+        // A table with a string key which cannot be written as an identifier uses brackets for all of its string keys
+        let use_bracket_keys = self.config.key_style == KeyStyle::Consistent
+            && table_constructor.fields().iter().any(|field| match field {
+                Field::ExpressionKey { key, .. } => {
+                    matches!(string_literal_value(key), Some(name) if !is_valid_identifier(name))
+                }
+                _ => false,
+            });
+
+        // Keys are rewritten once the fields are formatted, so the width of the table takes the rewritten keys into account
+        let mut table_width = braces_range.1 - braces_range.0;
+        if self.config.key_style != KeyStyle::Preserve {
+            for field in table_constructor.fields() {
+                if self.should_format_node(field) {
+                    let rewritten_field = self.format_field_key(field.to_owned(), use_bracket_keys);
+                    table_width =
+                        table_width + rewritten_field.to_string().len() - field.to_string().len();
+                }
+            }
+        }
+
         // We subtract 20 as we don't have full information about what preceded this table constructor (e.g. the assignment).
         // This is used as a general estimate. TODO: see if we can improve this calculation
-        let mut is_multiline = (braces_range.1 - braces_range.0) + self.get_indent_width()
-            > self.config.column_width - 20;
+        let mut is_multiline =
+            table_width + self.get_indent_width() > self.config.column_width - 20;
 
         // Determine if there are any comments within the table. If so, we should go multiline
         if !is_multiline {
@@ -202,8 +397,13 @@ impl CodeFormatter {
         let braces =
             self.create_table_braces(start_brace, end_brace, table_type, additional_indent_level);
 
+        // Whether each field is formatted, and so can be aligned with the fields around it
+        let mut alignable = Vec::new();
+
         while let Some(pair) = current_fields.next() {
             let (field, punctuation) = pair.into_tuple();
+            let should_format = self.should_format_node(&field);
+            alignable.push(should_format);
 
             let leading_trivia = match table_type {
                 TableType::MultiLine => {
@@ -223,7 +423,11 @@ impl CodeFormatter {
                 _ => FormatTriviaType::NoChange,
             };
 
-            let (formatted_field, mut trailing_trivia) = self.format_field(&field, leading_trivia);
+            let (mut formatted_field, mut trailing_trivia) =
+                self.format_field(&field, leading_trivia);
+            if should_format {
+                formatted_field = self.format_field_key(formatted_field, use_bracket_keys);
+            }
             // Filter trailing_trivia for any newlines
             trailing_trivia = trailing_trivia
                 .iter()
@@ -262,6 +466,12 @@ impl CodeFormatter {
             fields.push(Pair::new(formatted_field, formatted_punctuation))
         }
 
+        if self.config.align_assignments {
+            if let TableType::MultiLine = table_type {
+                fields = self.align_table_fields(fields, &alignable);
+            }
+        }
+
         TableConstructor::new()
             .with_braces(braces)
             .with_fields(fields)
@@ -30,6 +30,23 @@ pub fn trivia_contains_newline<'ast>(trivia_vec: impl Iterator<Item = &'ast Toke
     false
 }
 
+/// Whether formatted trivia contains a blank line, i.e. a newline which does not end a comment
+pub fn trivia_contains_blank_line(trivia_vec: &[&Token]) -> bool {
+    let mut previous_kind = None;
+    for trivia in trivia_vec {
+        if trivia_is_newline(trivia)
+            && !matches!(
+                previous_kind,
+                Some(TokenKind::SingleLineComment) | Some(TokenKind::MultiLineComment)
+            )
+        {
+            return true;
+        }
+        previous_kind = Some(trivia.token_kind());
+    }
+    false
+}
+
 pub fn can_hang_expression(expression: &Expression) -> bool {
     match expression {
         Expression::Parentheses { expression, .. } => can_hang_expression(expression),
@@ -96,7 +113,7 @@ fn indexed_type_info_trailing_trivia<'ast>(
 }
 
 #[cfg(feature = "luau")]
-fn type_info_trailing_trivia<'ast>(type_info: &TypeInfo<'ast>) -> Vec<Token<'ast>> {
+pub fn type_info_trailing_trivia<'ast>(type_info: &TypeInfo<'ast>) -> Vec<Token<'ast>> {
     match type_info {
         TypeInfo::Array { braces, .. } => {
             let (_, end_brace) = braces.tokens();
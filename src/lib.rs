@@ -1,8 +1,14 @@
-use anyhow::{format_err, Result};
+use full_moon::ast::Ast;
 use full_moon::visitors::VisitorMut;
 use serde::Deserialize;
+use std::any::Any;
+use std::cell::Cell;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
 
 mod formatters;
+mod verify_ast;
 
 /// The type of indents to use when indenting
 #[derive(Debug, Copy, Clone, Deserialize)]
@@ -54,6 +60,97 @@ impl Default for QuoteStyle {
     }
 }
 
+/// When to use parentheses around the arguments of a function call.
+/// Parentheses are always kept if there are any comments inside them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+pub enum CallParenType {
+    /// Always use parentheses around call arguments
+    Always,
+    /// Omit the parentheses when the only argument is a string literal, e.g. `require "module"`
+    NoSingleString,
+    /// Omit the parentheses when the only argument is a table constructor, e.g. `setup { ... }`
+    NoSingleTable,
+    /// Omit the parentheses when the only argument is a string literal or a table constructor
+    None,
+}
+
+impl Default for CallParenType {
+    fn default() -> Self {
+        CallParenType::Always
+    }
+}
+
+/// The case to use for letters within number literals, such as the `x` in `0xFF` or the `e` in `1e10`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+pub enum NumberCase {
+    /// Keep the case the letters were written in
+    Preserve,
+    /// Use lowercase letters
+    Lower,
+    /// Use uppercase letters
+    Upper,
+}
+
+impl Default for NumberCase {
+    fn default() -> Self {
+        NumberCase::Preserve
+    }
+}
+
+/// How to format single-line comments.
+/// Commented-out code and `stylua:` directives are never changed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+pub enum CommentStyle {
+    /// Keep comments as they are written, only removing trailing whitespace
+    Preserve,
+    /// Use a single space after `--`, and wrap comments longer than the column width onto multiple lines.
+    /// Doc comments starting with `---` and directives starting with `--!` are left as they are
+    Normalize,
+}
+
+impl Default for CommentStyle {
+    fn default() -> Self {
+        CommentStyle::Preserve
+    }
+}
+
+/// How to write string keys in tables, such as `["name"] = value`, and indexes, such as `t["name"]`.
+/// Keys are only written as identifiers when the string is a valid identifier, and not a keyword.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+pub enum KeyStyle {
+    /// Keep keys and indexes as they are written
+    Preserve,
+    /// Write keys as `name = value` and indexes as `t.name` wherever possible
+    Identifier,
+    /// As with `Identifier`, but when a table has a string key which cannot be written as an identifier,
+    /// every string key in that table is written as `["name"] = value`
+    Consistent,
+}
+
+impl Default for KeyStyle {
+    fn default() -> Self {
+        KeyStyle::Preserve
+    }
+}
+
+/// When to end statements with a semicolon.
+/// Semicolons which are needed to avoid ambiguous syntax are always kept.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+pub enum SemicolonStyle {
+    /// Remove any semicolons which are not needed
+    Remove,
+    /// Keep the semicolons that were written, but do not add any more
+    Preserve,
+    /// End every statement with a semicolon
+    Always,
+}
+
+impl Default for SemicolonStyle {
+    fn default() -> Self {
+        SemicolonStyle::Remove
+    }
+}
+
 /// An optional formatting range.
 /// If provided, only content within these boundaries (inclusive) will be formatted.
 /// Both boundaries are optional, and are given as byte offsets from the beginning of the file.
@@ -89,6 +186,43 @@ pub struct Config {
     indent_width: usize,
     /// The style of quotes to use in string literals.
     quote_style: QuoteStyle,
+    /// When to use parentheses around the arguments of a function call.
+    call_parentheses: CallParenType,
+    /// Whether to keep `if`, `do` and function declaration blocks containing a single short `return`, `break` or
+    /// function call on one line, such as `if not x then return end`.
+    collapse_simple_statement: bool,
+    /// The maximum number of consecutive blank lines to keep, such as between statements or table fields.
+    max_blank_lines: usize,
+    /// The maximum number of consecutive blank lines to keep between top-level statements.
+    /// If not set, `max_blank_lines` is used instead.
+    max_top_level_blank_lines: Option<usize>,
+    /// The case of the `0x` prefix of hexadecimal number literals.
+    hex_prefix_case: NumberCase,
+    /// The case of the digits `A` to `F` in hexadecimal number literals.
+    hex_digit_case: NumberCase,
+    /// The case of the `e` in number literals with an exponent.
+    exponent_case: NumberCase,
+    /// Whether to remove the redundant `+` from exponents, such as `1e+10`.
+    remove_exponent_plus: bool,
+    /// Whether to add a `0` to number literals ending in a decimal point, such as `1.`.
+    normalize_trailing_dot: bool,
+    /// Whether to reindent the lines of multi-line comments when the comment moves to a different indent level,
+    /// and remove any trailing whitespace within them.
+    format_multiline_comments: bool,
+    /// How to format single-line comments.
+    comment_style: CommentStyle,
+    /// Whether to line up the `=` of consecutive assignments, and of `name = value` fields in multi-line tables.
+    align_assignments: bool,
+    /// The maximum number of spaces to add before an `=` when aligning assignments.
+    /// Runs which would need more padding are split into separately aligned groups.
+    align_assignments_max_padding: usize,
+    /// How to write string keys in tables and indexes, such as `["name"] = value` and `t["name"]`.
+    key_style: KeyStyle,
+    /// When to end statements with a semicolon.
+    semicolons: SemicolonStyle,
+    /// Whether to verify the formatted output by reparsing it and checking it against the input code.
+    /// If the output has a different meaning to the input, an [`Error::Verification`] is returned instead.
+    verify: bool,
 }
 
 impl Config {
@@ -136,6 +270,125 @@ impl Config {
             ..self
         }
     }
+
+    /// Returns a new config with the given call parentheses style
+    pub fn with_call_parentheses(self, call_parentheses: CallParenType) -> Self {
+        Self {
+            call_parentheses,
+            ..self
+        }
+    }
+
+    /// Returns a new config with simple statements collapsed onto a single line, or always expanded
+    pub fn with_collapse_simple_statement(self, collapse_simple_statement: bool) -> Self {
+        Self {
+            collapse_simple_statement,
+            ..self
+        }
+    }
+
+    /// Returns a new config with the given maximum number of consecutive blank lines
+    pub fn with_max_blank_lines(self, max_blank_lines: usize) -> Self {
+        Self {
+            max_blank_lines,
+            ..self
+        }
+    }
+
+    /// Returns a new config with the given maximum number of consecutive blank lines between top-level statements
+    pub fn with_max_top_level_blank_lines(self, max_top_level_blank_lines: Option<usize>) -> Self {
+        Self {
+            max_top_level_blank_lines,
+            ..self
+        }
+    }
+
+    /// Returns a new config with the given case for hexadecimal prefixes
+    pub fn with_hex_prefix_case(self, hex_prefix_case: NumberCase) -> Self {
+        Self {
+            hex_prefix_case,
+            ..self
+        }
+    }
+
+    /// Returns a new config with the given case for hexadecimal digits
+    pub fn with_hex_digit_case(self, hex_digit_case: NumberCase) -> Self {
+        Self {
+            hex_digit_case,
+            ..self
+        }
+    }
+
+    /// Returns a new config with the given case for exponents
+    pub fn with_exponent_case(self, exponent_case: NumberCase) -> Self {
+        Self {
+            exponent_case,
+            ..self
+        }
+    }
+
+    /// Returns a new config with the `+` in exponents removed or kept
+    pub fn with_remove_exponent_plus(self, remove_exponent_plus: bool) -> Self {
+        Self {
+            remove_exponent_plus,
+            ..self
+        }
+    }
+
+    /// Returns a new config with number literals ending in a decimal point given a trailing `0`, or left as they are
+    pub fn with_normalize_trailing_dot(self, normalize_trailing_dot: bool) -> Self {
+        Self {
+            normalize_trailing_dot,
+            ..self
+        }
+    }
+
+    /// Returns a new config with multi-line comments reindented, or left as they are
+    pub fn with_format_multiline_comments(self, format_multiline_comments: bool) -> Self {
+        Self {
+            format_multiline_comments,
+            ..self
+        }
+    }
+
+    /// Returns a new config with the given style for single-line comments
+    pub fn with_comment_style(self, comment_style: CommentStyle) -> Self {
+        Self {
+            comment_style,
+            ..self
+        }
+    }
+
+    /// Returns a new config with the `=` of consecutive assignments and table fields aligned, or left unaligned
+    pub fn with_align_assignments(self, align_assignments: bool) -> Self {
+        Self {
+            align_assignments,
+            ..self
+        }
+    }
+
+    /// Returns a new config with the given maximum padding used when aligning assignments
+    pub fn with_align_assignments_max_padding(self, align_assignments_max_padding: usize) -> Self {
+        Self {
+            align_assignments_max_padding,
+            ..self
+        }
+    }
+
+    /// Returns a new config with the given style for string keys in tables and indexes
+    pub fn with_key_style(self, key_style: KeyStyle) -> Self {
+        Self { key_style, ..self }
+    }
+
+    /// Returns a new config with the given style for semicolons after statements
+    pub fn with_semicolons(self, semicolons: SemicolonStyle) -> Self {
+        Self { semicolons, ..self }
+    }
+
+    /// Returns a new config with output verification enabled or disabled
+    pub fn with_verify(self, verify: bool) -> Self {
+        Self { verify, ..self }
+    }
 }
 
 impl Default for Config {
@@ -146,21 +399,211 @@ impl Default for Config {
             indent_type: IndentType::Tabs,
             indent_width: 4,
             quote_style: QuoteStyle::default(),
+            call_parentheses: CallParenType::default(),
+            collapse_simple_statement: false,
+            max_blank_lines: 1,
+            max_top_level_blank_lines: None,
+            hex_prefix_case: NumberCase::default(),
+            hex_digit_case: NumberCase::default(),
+            exponent_case: NumberCase::default(),
+            remove_exponent_plus: false,
+            normalize_trailing_dot: false,
+            format_multiline_comments: true,
+            comment_style: CommentStyle::default(),
+            align_assignments: false,
+            align_assignments_max_padding: 10,
+            key_style: KeyStyle::default(),
+            semicolons: SemicolonStyle::default(),
+            verify: false,
+        }
+    }
+}
+
+/// An error that can occur whilst formatting code
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The input code could not be parsed.
+    Parse {
+        /// A description of why parsing failed
+        message: String,
+        /// The line the failure occurred on, starting at 1
+        line: usize,
+        /// The column the failure occurred at, starting at 1
+        column: usize,
+        /// The start and end byte offsets of the failing token
+        byte_range: (usize, usize),
+    },
+    /// The formatted output could not be verified against the input code.
+    Verification {
+        /// A description of why verification failed
+        message: String,
+    },
+    /// An unexpected failure occurred inside of the formatter. This is a bug in StyLua.
+    Internal {
+        /// A description of the failure
+        message: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parse {
+                message,
+                line,
+                column,
+                ..
+            } => write!(
+                f,
+                "error parsing: {} at line {}, column {}",
+                message, line, column
+            ),
+            Error::Verification { message } => write!(f, "verification failed: {}", message),
+            Error::Internal { message } => write!(f, "internal error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Returns the byte offset of the given 1-indexed line and column within the code
+fn byte_offset(code: &str, line: usize, column: usize) -> usize {
+    let line_start: usize = code
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+
+    code[line_start..]
+        .char_indices()
+        .nth(column.saturating_sub(1))
+        .map_or(code.len(), |(offset, _)| line_start + offset)
+}
+
+impl Error {
+    /// Creates a parse error from the error returned by full_moon
+    fn from_parse_error(code: &str, error: full_moon::Error) -> Self {
+        match error {
+            full_moon::Error::AstError(full_moon::ast::AstError::UnexpectedToken {
+                token,
+                additional,
+            }) => Error::Parse {
+                message: match additional {
+                    Some(additional) => format!("unexpected token `{}` ({})", token, additional),
+                    None => format!("unexpected token `{}`", token),
+                },
+                line: token.start_position().line(),
+                column: token.start_position().character(),
+                byte_range: (token.start_position().bytes(), token.end_position().bytes()),
+            },
+            full_moon::Error::TokenizerError(error) => {
+                // full_moon does not expose the position of a tokenizer error, only through its message
+                lazy_static::lazy_static! {
+                    static ref POSITION: regex::Regex = regex::Regex::new(r"^(.*) at line (\d+), column (\d+)$").unwrap();
+                }
+                let error = error.to_string();
+                match POSITION.captures(&error) {
+                    Some(captures) => {
+                        let line = captures[2].parse().unwrap_or(1);
+                        let column = captures[3].parse().unwrap_or(1);
+                        let start = byte_offset(code, line, column);
+                        let end = code[start..]
+                            .chars()
+                            .next()
+                            .map_or(start, |c| start + c.len_utf8());
+
+                        Error::Parse {
+                            message: captures[1].to_string(),
+                            line,
+                            column,
+                            byte_range: (start, end),
+                        }
+                    }
+                    None => Error::Parse {
+                        message: error,
+                        line: 1,
+                        column: 1,
+                        byte_range: (0, 0),
+                    },
+                }
+            }
+            other => Error::Parse {
+                message: other.to_string(),
+                line: 1,
+                column: 1,
+                byte_range: (0, 0),
+            },
         }
     }
 }
 
+fn parse(code: &str) -> Result<Ast<'_>, Error> {
+    full_moon::parse(code).map_err(|error| Error::from_parse_error(code, error))
+}
+
+/// Checks that the given code can be parsed, without formatting it.
+/// Returns the same parse errors as [`format_code`].
+pub fn check_syntax(code: &str) -> Result<(), Error> {
+    parse(code).map(|_| ())
+}
+
+thread_local! {
+    /// Whether this thread is currently running the formatter, so that any panic it raises is caught and reported as an error
+    static FORMATTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs the given closure, catching any panic it raises without printing the panic message.
+/// The panic hook is shared between threads, so it is only replaced once, with a hook which stays quiet
+/// whilst the formatter is running on the current thread and otherwise defers to the previous hook
+fn catch_formatter_panic<T>(f: impl FnOnce() -> T) -> Result<T, Box<dyn Any + Send>> {
+    static INSTALL_HOOK: Once = Once::new();
+    INSTALL_HOOK.call_once(|| {
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !FORMATTING.with(Cell::get) {
+                previous_hook(info);
+            }
+        }));
+    });
+
+    FORMATTING.with(|formatting| formatting.set(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    FORMATTING.with(|formatting| formatting.set(false));
+    result
+}
+
 /// Formats given Lua code
-pub fn format_code(code: &str, config: Config, range: Option<Range>) -> Result<String> {
-    let mut ast = match full_moon::parse(&code) {
+pub fn format_code(code: &str, config: Config, range: Option<Range>) -> Result<String, Error> {
+    let mut ast = parse(code)?;
+
+    // Keep a copy of the original AST to verify the output against
+    let input_ast = if config.verify {
+        Some(ast.to_owned())
+    } else {
+        None
+    };
+
+    // The formatter panics on nodes it cannot handle, so we surface these as internal errors rather than aborting
+    let mut code_formatter = formatters::CodeFormatter::new(config, range);
+    ast = match catch_formatter_panic(|| code_formatter.visit_ast(ast)) {
         Ok(ast) => ast,
-        Err(error) => {
-            return Err(format_err!("error parsing: {}", error));
+        Err(payload) => {
+            let message = match payload.downcast::<String>() {
+                Ok(message) => *message,
+                Err(payload) => match payload.downcast::<&str>() {
+                    Ok(message) => message.to_string(),
+                    Err(_) => String::from("formatter panicked"),
+                },
+            };
+            return Err(Error::Internal { message });
         }
     };
 
-    let mut code_formatter = formatters::CodeFormatter::new(config, range);
-    ast = code_formatter.visit_ast(ast);
+    let output = full_moon::print(&ast);
+
+    if let Some(input_ast) = input_ast {
+        verify_ast::verify(input_ast, &output)?;
+    }
 
-    Ok(full_moon::print(&ast))
+    Ok(output)
 }
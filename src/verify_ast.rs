@@ -0,0 +1,371 @@
+use crate::Error;
+#[cfg(feature = "luau")]
+use full_moon::ast::types::TypeInfo;
+use full_moon::ast::{
+    punctuated::{Pair, Punctuated},
+    span::ContainedSpan,
+    Ast, Block, Expression, Field, FunctionArgs, Index, TableConstructor, Value,
+};
+use full_moon::node::Node;
+use full_moon::tokenizer::{
+    Position, StringLiteralQuoteType, Symbol, Token, TokenReference, TokenType,
+};
+use full_moon::visitors::VisitorMut;
+
+/// Creates a pair of tokens, such as parentheses, which does not point to any position in the code
+fn create_contained_span<'ast>(start: Symbol, end: Symbol) -> ContainedSpan<'ast> {
+    ContainedSpan::new(
+        TokenReference::new(
+            vec![],
+            Token::new(TokenType::Symbol { symbol: start }),
+            vec![],
+        ),
+        TokenReference::new(
+            vec![],
+            Token::new(TokenType::Symbol { symbol: end }),
+            vec![],
+        ),
+    )
+}
+
+/// Creates a parentheses token which does not point to any position in the code
+fn create_parentheses<'ast>() -> ContainedSpan<'ast> {
+    create_contained_span(Symbol::LeftParen, Symbol::RightParen)
+}
+
+/// Creates a string literal expression from an identifier, such as the `name` in `t.name`
+fn create_string_key<'ast>(name: &TokenReference<'ast>) -> Expression<'ast> {
+    Expression::Value {
+        value: Box::new(Value::String(TokenReference::new(
+            vec![],
+            Token::new(TokenType::StringLiteral {
+                literal: name.token().to_string().into(),
+                multi_line: None,
+                quote_type: StringLiteralQuoteType::Double,
+            }),
+            vec![],
+        ))),
+        #[cfg(feature = "luau")]
+        type_assertion: None,
+    }
+}
+
+/// Rewrites an AST so that two ASTs can be compared, removing any differences that StyLua is allowed to introduce
+/// whilst formatting without changing the meaning of the code.
+/// The rewritten AST is only used for comparison, and is not guaranteed to be valid Lua.
+struct AstNormaliser;
+
+impl<'ast> VisitorMut<'ast> for AstNormaliser {
+    fn visit_block_end(&mut self, node: Block<'ast>) -> Block<'ast> {
+        // Semicolons are added and removed by the formatter, so are ignored
+        let stmts = node.stmts().map(|stmt| (stmt.to_owned(), None)).collect();
+        let last_stmt = node
+            .last_stmt()
+            .map(|last_stmt| (last_stmt.to_owned(), None));
+
+        node.with_stmts(stmts).with_last_stmt(last_stmt)
+    }
+
+    fn visit_expression_end(&mut self, node: Expression<'ast>) -> Expression<'ast> {
+        match node {
+            Expression::Parentheses {
+                contained,
+                expression,
+            } => match &*expression {
+                // Parentheses around a call or varargs truncate the values returned, so they must be kept
+                Expression::Value { value, .. }
+                    if matches!(&**value, Value::FunctionCall(_))
+                        || matches!(&**value, Value::Symbol(token) if matches!(token.token_type(), TokenType::Symbol { symbol: Symbol::Ellipse })) =>
+                {
+                    Expression::Parentheses {
+                        contained,
+                        expression,
+                    }
+                }
+                // Any other parentheses only affect precedence, which is made explicit below
+                _ => *expression,
+            },
+            // Wrap all operators in parentheses, so that precedence is visible in the tokens
+            Expression::BinaryOperator { .. } | Expression::UnaryOperator { .. } => {
+                Expression::Parentheses {
+                    contained: create_parentheses(),
+                    expression: Box::new(node),
+                }
+            }
+            _ => node,
+        }
+    }
+
+    fn visit_table_constructor_end(
+        &mut self,
+        node: TableConstructor<'ast>,
+    ) -> TableConstructor<'ast> {
+        // Trailing separators in tables are optional
+        let mut fields = node.fields().to_owned();
+        if let Some(pair) = fields.pop() {
+            fields.push(Pair::new(pair.into_value(), None));
+        }
+
+        node.with_fields(fields)
+    }
+
+    fn visit_field_end(&mut self, node: Field<'ast>) -> Field<'ast> {
+        // Fields such as `name = value` are equivalent to `["name"] = value`
+        match node {
+            Field::NameKey { key, equal, value } => Field::ExpressionKey {
+                brackets: create_contained_span(Symbol::LeftBracket, Symbol::RightBracket),
+                key: create_string_key(&key),
+                equal,
+                value,
+            },
+            _ => node,
+        }
+    }
+
+    fn visit_index_end(&mut self, node: Index<'ast>) -> Index<'ast> {
+        // Indexes such as `t.name` are equivalent to `t["name"]`
+        match node {
+            Index::Dot { name, .. } => Index::Brackets {
+                brackets: create_contained_span(Symbol::LeftBracket, Symbol::RightBracket),
+                expression: create_string_key(&name),
+            },
+            _ => node,
+        }
+    }
+
+    #[cfg(feature = "luau")]
+    fn visit_type_info_end(&mut self, node: TypeInfo<'ast>) -> TypeInfo<'ast> {
+        // Trailing separators in table types are optional
+        match node {
+            TypeInfo::Table { braces, mut fields } => {
+                if let Some(pair) = fields.pop() {
+                    fields.push(Pair::new(pair.into_value(), None));
+                }
+
+                TypeInfo::Table { braces, fields }
+            }
+            _ => node,
+        }
+    }
+
+    fn visit_function_args_end(&mut self, node: FunctionArgs<'ast>) -> FunctionArgs<'ast> {
+        // Calls such as `foo "bar"` and `foo { bar }` are equivalent to `foo("bar")` and `foo({ bar })`
+        let value = match node {
+            FunctionArgs::String(token) => Value::String(token),
+            FunctionArgs::TableConstructor(table_constructor) => {
+                Value::TableConstructor(table_constructor)
+            }
+            _ => return node,
+        };
+
+        let mut arguments = Punctuated::new();
+        arguments.push(Pair::new(
+            Expression::Value {
+                value: Box::new(value),
+                #[cfg(feature = "luau")]
+                type_assertion: None,
+            },
+            None,
+        ));
+
+        FunctionArgs::Parentheses {
+            parentheses: create_parentheses(),
+            arguments,
+        }
+    }
+}
+
+/// Returns the value of a quoted string literal, with any escapes which do not change its value removed
+fn normalise_string(literal: &str) -> String {
+    lazy_static::lazy_static! {
+        static ref ESCAPES: regex::Regex = regex::Regex::new(r#"\\([\S\s])"#).unwrap();
+        static ref UNNECESSARY_ESCAPES: regex::Regex = regex::Regex::new(r#"^[^\n\r0-9\\abfnrtuvxz]$"#).unwrap();
+    }
+
+    ESCAPES
+        .replace_all(literal, |caps: &regex::Captures| {
+            let escaped = &caps[1];
+            if UNNECESSARY_ESCAPES.is_match(escaped) {
+                escaped.to_owned()
+            } else {
+                format!("\\{}", escaped)
+            }
+        })
+        .into_owned()
+}
+
+/// Returns a number literal with the rewrites made by the formatter undone, so that the same literal compares equal.
+/// Only the way the literal is written is normalised, so integers and floats, or numbers which only compare equal
+/// once rounded to a float, are still seen as different
+fn normalise_number(text: &str) -> String {
+    let text = text.to_lowercase();
+    if text.starts_with("0x") {
+        return text;
+    }
+
+    let (mantissa, exponent) = text.split_at(text.find('e').unwrap_or(text.len()));
+    let mut mantissa = mantissa.to_owned();
+    if mantissa.starts_with('.') {
+        mantissa.insert(0, '0');
+    }
+    if mantissa.ends_with('.') {
+        mantissa.push('0');
+    }
+
+    mantissa + &exponent.replacen("e+", "e", 1)
+}
+
+/// A token in a normalised AST, alongside where it is found in the code
+struct NormalisedToken {
+    text: String,
+    position: Position,
+}
+
+fn normalised_tokens(ast: Ast) -> Vec<NormalisedToken> {
+    let ast = AstNormaliser.visit_ast(ast);
+
+    ast.nodes()
+        .tokens()
+        .filter_map(|token_reference| {
+            let token = token_reference.token();
+            let text = match token.token_type() {
+                TokenType::Whitespace { .. } => return None,
+                TokenType::StringLiteral {
+                    literal,
+                    quote_type,
+                    ..
+                } => match quote_type {
+                    StringLiteralQuoteType::Brackets => token.to_string(),
+                    _ => format!("\"{}\"", normalise_string(literal)),
+                },
+                TokenType::Number { text } => normalise_number(text),
+                _ => token.to_string(),
+            };
+
+            Some(NormalisedToken {
+                text,
+                position: token.start_position(),
+            })
+        })
+        .collect()
+}
+
+/// Finds the position of the token at the given index.
+/// Tokens created during normalisation have no position, so the closest following token is used instead.
+fn position_at(tokens: &[NormalisedToken], index: usize) -> Position {
+    tokens
+        .iter()
+        .skip(index)
+        .chain(tokens.iter().take(index).rev())
+        .map(|token| token.position)
+        .find(|position| position.line() != 0)
+        .unwrap_or_default()
+}
+
+/// Checks that the formatted output parses to the same AST as the input, once trivia and any normalisations
+/// made by the formatter (such as quotes, excess parentheses and semicolons) are taken into account.
+pub fn verify(input: Ast, output: &str) -> Result<(), Error> {
+    let output = match full_moon::parse(output) {
+        Ok(ast) => ast,
+        Err(error) => {
+            return Err(Error::Verification {
+                message: format!("formatted output could not be parsed: {}", error),
+            })
+        }
+    };
+
+    let input_tokens = normalised_tokens(input);
+    let output_tokens = normalised_tokens(output);
+
+    let divergence = input_tokens
+        .iter()
+        .zip(output_tokens.iter())
+        .position(|(input, output)| input.text != output.text)
+        .or_else(|| {
+            if input_tokens.len() != output_tokens.len() {
+                Some(input_tokens.len().min(output_tokens.len()))
+            } else {
+                None
+            }
+        });
+
+    match divergence {
+        Some(index) => {
+            let input_position = position_at(&input_tokens, index);
+            let output_position = position_at(&output_tokens, index);
+            let describe = |tokens: &[NormalisedToken]| match tokens.get(index) {
+                Some(token) => format!("`{}`", token.text),
+                None => String::from("end of file"),
+            };
+
+            Err(Error::Verification {
+                message: format!(
+                    "formatted output differs from input: expected {} at line {}, column {}, but found {} at line {}, column {} of the output",
+                    describe(&input_tokens),
+                    input_position.line(),
+                    input_position.character(),
+                    describe(&output_tokens),
+                    output_position.line(),
+                    output_position.character(),
+                ),
+            })
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verify_code(input: &str, output: &str) -> Result<(), Error> {
+        verify(full_moon::parse(input).unwrap(), output)
+    }
+
+    #[test]
+    fn test_accepts_normalisations() {
+        verify_code("local x = ('a');", "local x = \"a\"\n").unwrap();
+        verify_code("local x = \"\\'\"", "local x = \"'\"\n").unwrap();
+        verify_code("local x = 0XFF", "local x = 0xff\n").unwrap();
+        verify_code("local x = .5 + 1. + 1E+10", "local x = 0.5 + 1.0 + 1e10\n").unwrap();
+    }
+
+    #[test]
+    fn test_rejects_changed_ast() {
+        match verify_code("local x = a + b", "local x = a - b\n") {
+            Err(Error::Verification { message }) => assert_eq!(
+                message,
+                "formatted output differs from input: expected `+` at line 1, column 13, but found `-` at line 1, column 13 of the output"
+            ),
+            other => panic!("expected a verification error, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_rejects_changed_number() {
+        for (input, output) in [
+            ("local x = 1", "local x = 1.0\n"),
+            ("local x = 9007199254740993", "local x = 9007199254740992\n"),
+            ("local x = 0x10", "local x = 16\n"),
+        ] {
+            assert!(matches!(
+                verify_code(input, output),
+                Err(Error::Verification { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_rejects_changed_decimal_escape() {
+        for (input, output) in [
+            ("local x = \"\\9\"", "local x = \"9\"\n"),
+            ("local x = \"\\8\"", "local x = \"8\"\n"),
+            ("local x = \"\\0\"", "local x = \"0\"\n"),
+        ] {
+            assert!(matches!(
+                verify_code(input, output),
+                Err(Error::Verification { .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,68 @@
+// Each test crate only uses some of these helpers
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// Creates an empty directory to run StyLua in, containing the given files
+pub fn create_project<P: AsRef<str>, C: AsRef<str>>(name: &str, files: &[(P, C)]) -> PathBuf {
+    let root = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    if root.exists() {
+        fs::remove_dir_all(&root).unwrap();
+    }
+    fs::create_dir_all(&root).unwrap();
+
+    for (path, contents) in files {
+        let path = root.join(path.as_ref());
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents.as_ref()).unwrap();
+    }
+
+    root
+}
+
+/// Reads a file from a project created with `create_project`
+pub fn read(root: &Path, path: &str) -> String {
+    fs::read_to_string(root.join(path)).unwrap()
+}
+
+/// Creates a command to run StyLua in the given directory
+pub fn stylua(cwd: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_stylua"));
+    command.current_dir(cwd);
+    command
+}
+
+/// Runs StyLua in the given directory, returning its output
+pub fn run_stylua(cwd: &Path, args: &[&str]) -> Output {
+    stylua(cwd).args(args).output().unwrap()
+}
+
+/// Runs StyLua in the given directory, passing the given code to it through stdin
+pub fn run_stylua_with_stdin(cwd: &Path, args: &[&str], input: &str) -> Output {
+    let mut child = stylua(cwd)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Asserts that StyLua exited successfully, showing any errors it output otherwise
+pub fn assert_success(output: &Output) {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,8 @@
+type Foo = {
+	name:   string,
+	-- stylua: ignore
+	value:   number , -- comment
+	[string]:   any,
+	-- stylua: ignore
+	last:   boolean
+}
@@ -0,0 +1,27 @@
+local a = (b + c)
+local d = (b * c) + e
+local f = b + (c * e)
+local g = (b + c) * e
+local h = b - (c - e)
+local i = (b - c) - e
+local j = b .. (c .. e)
+local k = (b .. c) .. e
+local l = 2 ^ (3 ^ 4)
+local m = (2 ^ 3) ^ 4
+local n = -(b + c)
+local o = -(b ^ c)
+local p = (-b) ^ c
+local q = b ^ (-c)
+local r = -(-b)
+local s = not (not b)
+local t = (-b).x
+local u = (b + c):foo()
+local v = (f()) + 1
+local w = (...) + 1
+local x = (b and c) or e
+local y = b and (c or e)
+local z = (b < c) == e
+local aa = ((b + c)) * e
+if (b and c) then
+end
+return (b + c)
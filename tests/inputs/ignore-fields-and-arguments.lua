@@ -0,0 +1,21 @@
+local t = {
+	a   =   1,
+	-- stylua: ignore
+	matrix = { 1, 0,
+	           0, 1 },
+	b   =   function()   return   1   end,
+	-- stylua: ignore
+	c   =   function()   return   1   end,
+	-- stylua: ignore
+	d   =   2 , -- comment
+	-- stylua: ignore
+	e   =   3
+}
+
+call(
+	foo,
+	-- stylua: ignore
+	{ 1, 0,
+	  0, 1 },
+	bar   +   baz
+)
@@ -0,0 +1,20 @@
+local   a=1
+-- stylua: ignore start
+local  lookup = {
+    a   = 1,   b = 2,
+}
+if   x then
+      local   y=1
+end
+-- stylua: ignore end
+local   b=2
+function foo()
+   local   c=3
+   -- stylua: ignore start
+   local   d   =   4
+   return   d
+end
+-- stylua: ignore
+if   x then
+  local   y=1
+end
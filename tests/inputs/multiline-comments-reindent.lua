@@ -0,0 +1,16 @@
+local function outer()
+    if true then
+        --[==[   
+            A comment with trailing whitespace   
+
+          and a less indented line
+        ]==]
+        local x = 1
+    end
+end
+
+        --[[
+        Moved to the top level
+            keeping its shape
+        ]]
+local y = 2
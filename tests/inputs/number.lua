@@ -5,4 +5,12 @@ local d = 5e-5
 local e = -.5
 local f = .2e-5
 local g = -.1e+5
-local h = 0x12
\ No newline at end of file
+local h = 0x12
+local i = 0XfF
+local j = 0xABCDEF
+local k = 1E10
+local l = 1e+10
+local m = 2.5E-3
+local n = 1.
+local o = 1.e5
+local p = 0e5
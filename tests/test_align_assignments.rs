@@ -0,0 +1,152 @@
+use stylua_lib::{format_code, Config};
+
+fn format(input: &str, config: Config) -> String {
+    format_code(input, config.with_verify(true), None).unwrap()
+}
+
+const INPUT: &str = r###"
+local a = 1
+local bcd = 2 -- comment
+-- comment line
+x.y.z = 3
+local e
+local f = 4
+
+local g = 5
+local long_variable_name_here = 6
+local h = 7
+local t = {
+    a = 1,
+    bcd = 2,
+    [x] = 3,
+    ef = { x = 1, yy = 2 },
+
+    ghi = 4,
+    j = 5,
+}
+"###;
+
+#[test]
+fn test_disabled() {
+    insta::assert_snapshot!(format(INPUT, Config::default()), @r###"
+    local a = 1
+    local bcd = 2 -- comment
+    -- comment line
+    x.y.z = 3
+    local e
+    local f = 4
+
+    local g = 5
+    local long_variable_name_here = 6
+    local h = 7
+    local t = {
+    	a = 1,
+    	bcd = 2,
+    	[x] = 3,
+    	ef = { x = 1, yy = 2 },
+
+    	ghi = 4,
+    	j = 5,
+    }
+    "###);
+}
+
+#[test]
+fn test_align_assignments() {
+    insta::assert_snapshot!(format(INPUT, Config::default().with_align_assignments(true)), @r###"
+    local a   = 1
+    local bcd = 2 -- comment
+    -- comment line
+    x.y.z     = 3
+    local e
+    local f = 4
+
+    local g = 5
+    local long_variable_name_here = 6
+    local h = 7
+    local t = {
+    	a   = 1,
+    	bcd = 2,
+    	[x] = 3,
+    	ef = { x = 1, yy = 2 },
+
+    	ghi = 4,
+    	j   = 5,
+    }
+    "###);
+}
+
+#[test]
+fn test_max_padding() {
+    insta::assert_snapshot!(
+        format(
+            INPUT,
+            Config::default()
+                .with_align_assignments(true)
+                .with_align_assignments_max_padding(30)
+        ),
+        @r###"
+    local a   = 1
+    local bcd = 2 -- comment
+    -- comment line
+    x.y.z     = 3
+    local e
+    local f = 4
+
+    local g                       = 5
+    local long_variable_name_here = 6
+    local h                       = 7
+    local t                       = {
+    	a   = 1,
+    	bcd = 2,
+    	[x] = 3,
+    	ef = { x = 1, yy = 2 },
+
+    	ghi = 4,
+    	j   = 5,
+    }
+    "###
+    );
+}
+
+#[test]
+fn test_ignored_statement() {
+    insta::assert_snapshot!(
+        format(
+            "local a = 1\n-- stylua: ignore\nlocal bcd   =   2\nlocal ef = 3\n",
+            Config::default().with_align_assignments(true)
+        ),
+        @r###"
+    local a = 1
+    -- stylua: ignore
+    local bcd   =   2
+    local ef = 3
+    "###
+    );
+}
+
+#[test]
+fn test_column_width() {
+    // Runs are left unaligned when the padding would push a line past the column width
+    insta::assert_snapshot!(format(r###"
+local abcdefgh = 1
+local x = "a string of some length"
+local t = {
+    abcdefgh = 1,
+    x = "a string of some length",
+}
+
+local y = 1
+local zz = 2
+"###, Config::default().with_align_assignments(true).with_column_width(40)), @r###"
+    local abcdefgh = 1
+    local x = "a string of some length"
+    local t = {
+    	abcdefgh = 1,
+    	x = "a string of some length",
+    }
+
+    local y  = 1
+    local zz = 2
+    "###);
+}
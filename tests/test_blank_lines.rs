@@ -0,0 +1,126 @@
+use stylua_lib::{format_code, Config};
+
+fn format(input: &str, config: Config) -> String {
+    format_code(input, config, None).unwrap()
+}
+
+const INPUT: &str = r###"
+local a = 1
+
+
+
+local b = 2
+local function f()
+    local c = 1
+
+
+
+    local d = {
+        x = 1,
+
+
+
+        y = 2,
+    }
+end
+
+
+
+-- comment at the end of the file
+"###;
+
+#[test]
+fn test_default_max_blank_lines() {
+    insta::assert_snapshot!(format(INPUT, Config::default()), @r###"
+    local a = 1
+
+    local b = 2
+    local function f()
+    	local c = 1
+
+    	local d = {
+    		x = 1,
+
+    		y = 2,
+    	}
+    end
+
+    -- comment at the end of the file
+    "###);
+}
+
+#[test]
+fn test_max_blank_lines() {
+    insta::assert_snapshot!(format(INPUT, Config::default().with_max_blank_lines(2)), @r###"
+    local a = 1
+
+
+    local b = 2
+    local function f()
+    	local c = 1
+
+
+    	local d = {
+    		x = 1,
+
+
+    		y = 2,
+    	}
+    end
+
+
+    -- comment at the end of the file
+    "###);
+}
+
+#[test]
+fn test_no_blank_lines() {
+    insta::assert_snapshot!(format(INPUT, Config::default().with_max_blank_lines(0)), @r###"
+    local a = 1
+    local b = 2
+    local function f()
+    	local c = 1
+    	local d = {
+    		x = 1,
+    		y = 2,
+    	}
+    end
+    -- comment at the end of the file
+    "###);
+}
+
+#[test]
+fn test_max_top_level_blank_lines() {
+    insta::assert_snapshot!(format(INPUT, Config::default().with_max_top_level_blank_lines(Some(2))), @r###"
+    local a = 1
+
+
+    local b = 2
+    local function f()
+    	local c = 1
+
+    	local d = {
+    		x = 1,
+
+    		y = 2,
+    	}
+    end
+
+
+    -- comment at the end of the file
+    "###);
+}
+
+#[test]
+fn test_max_top_level_blank_lines_in_table() {
+    insta::assert_snapshot!(format("local t = {\n\tx = 1,\n\n\n\n\ty = 2,\n}\n\n\n\nreturn t\n", Config::default().with_max_top_level_blank_lines(Some(2))), @r###"
+    local t = {
+    	x = 1,
+
+    	y = 2,
+    }
+
+
+    return t
+    "###);
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use common::{create_project, stylua};
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+use std::time::SystemTime;
+
+fn run_stylua(cwd: &Path, args: &[&str]) -> Output {
+    stylua(cwd)
+        .args(args)
+        .env_remove("XDG_CACHE_HOME")
+        .output()
+        .unwrap()
+}
+
+fn modified(root: &Path, path: &str) -> SystemTime {
+    fs::metadata(root.join(path)).unwrap().modified().unwrap()
+}
+
+#[test]
+fn test_cache_skips_formatted_files() {
+    let root = create_project(
+        "cache_skips_formatted_files",
+        &[("a.lua", "local x = 1\n"), ("b.lua", "local   y=2\n")],
+    );
+
+    let output = run_stylua(&root, &["--cache", "."]);
+    assert!(output.status.success());
+
+    let cache = fs::read_to_string(root.join(".stylua-cache")).unwrap();
+    assert!(cache.starts_with(concat!("stylua-cache ", env!("CARGO_PKG_VERSION"), "\n")));
+    assert_eq!(cache.lines().count(), 3);
+
+    // Files known to be formatted are not written to again
+    let a_modified = modified(&root, "a.lua");
+    let b_modified = modified(&root, "b.lua");
+    let output = run_stylua(&root, &["--cache", "."]);
+    assert!(output.status.success());
+    assert_eq!(modified(&root, "a.lua"), a_modified);
+    assert_eq!(modified(&root, "b.lua"), b_modified);
+    assert_eq!(
+        fs::read_to_string(root.join("b.lua")).unwrap(),
+        "local y = 2\n"
+    );
+}
+
+#[test]
+fn test_cache_normalizes_paths() {
+    let root = create_project(
+        "cache_normalizes_paths",
+        &[("a.lua", "local x = 1\n"), ("src/b.lua", "local y = 2\n")],
+    );
+
+    // The same file given through different paths has a single entry in the cache
+    let output = run_stylua(&root, &["--cache", "a.lua", "src/../a.lua", "./src/b.lua"]);
+    assert!(output.status.success());
+    let output = run_stylua(&root, &["--cache", "src/b.lua"]);
+    assert!(output.status.success());
+
+    let cache = fs::read_to_string(root.join(".stylua-cache")).unwrap();
+    assert_eq!(cache.lines().count(), 3);
+    assert!(!cache.contains(".."));
+}
+
+#[test]
+fn test_no_cache() {
+    let root = create_project("no_cache", &[("a.lua", "local x = 1\n")]);
+
+    let output = run_stylua(&root, &["--cache", "--no-cache", "."]);
+    assert!(output.status.success());
+    assert!(!root.join(".stylua-cache").exists());
+}
+
+#[test]
+fn test_cache_invalidated_by_changes() {
+    let root = create_project(
+        "cache_invalidated_by_changes",
+        &[
+            ("a.lua", "local x = 1\n"),
+            ("b.lua", "if x then\n\tlocal y = 2\nend\n"),
+        ],
+    );
+
+    let output = run_stylua(&root, &["--check", "--cache", "."]);
+    assert_eq!(output.status.code(), Some(0));
+
+    // Changing the contents of a file means it is checked again
+    fs::write(root.join("a.lua"), "local x =1 \n").unwrap();
+    let output = run_stylua(&root, &["--check", "--cache", "."]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("a.lua"));
+    assert!(!stdout.contains("b.lua"));
+
+    // Changing the configuration means every file is checked again
+    fs::write(root.join("a.lua"), "local x = 1\n").unwrap();
+    fs::write(root.join("stylua.toml"), "indent_type = \"Spaces\"\n").unwrap();
+    let output = run_stylua(&root, &["--check", "--cache", "."]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("a.lua"));
+    assert!(stdout.contains("b.lua"));
+}
+
+#[test]
+fn test_cache_location() {
+    let root = create_project("cache_location", &[("a.lua", "local x = 1\n")]);
+
+    let output = run_stylua(&root, &["--cache-location", "cache/stylua", "."]);
+    assert!(output.status.success());
+    assert!(root.join("cache/stylua").is_file());
+    assert!(!root.join(".stylua-cache").exists());
+
+    let output = stylua(&root)
+        .args(["--cache", "a.lua"])
+        .env("XDG_CACHE_HOME", root.join("xdg"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(fs::read_dir(root.join("xdg/stylua")).unwrap().count(), 1);
+    assert!(!root.join(".stylua-cache").exists());
+}
@@ -0,0 +1,108 @@
+use stylua_lib::{format_code, CallParenType, Config};
+
+fn format(input: &str, call_parentheses: CallParenType) -> String {
+    format_code(
+        input,
+        Config::default()
+            .with_call_parentheses(call_parentheses)
+            .with_verify(true),
+        None,
+    )
+    .unwrap()
+}
+
+const INPUT: &str = r###"
+local foo = require("foo")
+local bar = require "bar"
+setup({ enabled = true })
+setup { enabled = false }
+obj:method("baz") -- comment
+call("a", "b")
+call(("a"))
+local value = require("module").value
+local chained = get("a"):with({})
+"###;
+
+#[test]
+fn test_always() {
+    insta::assert_snapshot!(format(INPUT, CallParenType::Always), @r###"
+
+    local foo = require("foo")
+    local bar = require("bar")
+    setup({ enabled = true })
+    setup({ enabled = false })
+    obj:method("baz") -- comment
+    call("a", "b")
+    call(("a"))
+    local value = require("module").value
+    local chained = get("a"):with({})
+    "###);
+}
+
+#[test]
+fn test_no_single_string() {
+    insta::assert_snapshot!(format(INPUT, CallParenType::NoSingleString), @r###"
+
+    local foo = require "foo"
+    local bar = require "bar"
+    setup({ enabled = true })
+    setup({ enabled = false })
+    obj:method "baz" -- comment
+    call("a", "b")
+    call(("a"))
+    local value = require("module").value
+    local chained = get("a"):with({})
+    "###);
+}
+
+#[test]
+fn test_no_single_table() {
+    insta::assert_snapshot!(format(INPUT, CallParenType::NoSingleTable), @r###"
+
+    local foo = require("foo")
+    local bar = require("bar")
+    setup { enabled = true }
+    setup { enabled = false }
+    obj:method("baz") -- comment
+    call("a", "b")
+    call(("a"))
+    local value = require("module").value
+    local chained = get("a"):with {}
+    "###);
+}
+
+#[test]
+fn test_none() {
+    insta::assert_snapshot!(format(INPUT, CallParenType::None), @r###"
+
+    local foo = require "foo"
+    local bar = require "bar"
+    setup { enabled = true }
+    setup { enabled = false }
+    obj:method "baz" -- comment
+    call("a", "b")
+    call(("a"))
+    local value = require("module").value
+    local chained = get("a"):with {}
+    "###);
+}
+
+#[test]
+fn test_comments_keep_parentheses() {
+    insta::assert_snapshot!(format(r###"
+call(--[[ comment ]] "a")
+call({
+    -- comment
+    a = 1,
+})
+"###, CallParenType::None), @r###"
+
+    call(--[[ comment ]]
+    	"a"
+    )
+    call {
+    	-- comment
+    	a = 1,
+    }
+    "###);
+}
@@ -0,0 +1,122 @@
+use stylua_lib::{format_code, Config};
+
+fn format(input: &str) -> String {
+    format_code(
+        input,
+        Config::default()
+            .with_collapse_simple_statement(true)
+            .with_column_width(80)
+            .with_verify(true),
+        None,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_collapse_if() {
+    insta::assert_snapshot!(format(r###"
+if not x then
+    return
+end
+while true do
+    if y then break end
+end
+if z then
+    print("z")
+end
+if x then return 1 else return 2 end
+if x then foo() bar() end
+"###), @r###"
+    if not x then return end
+    while true do
+    	if y then break end
+    end
+    if z then print("z") end
+    if x then
+    	return 1
+    else
+    	return 2
+    end
+    if x then
+    	foo()
+    	bar()
+    end
+    "###);
+}
+
+#[test]
+fn test_collapse_functions_and_do() {
+    insta::assert_snapshot!(format(r###"
+function M.add(a, b)
+    return a + b
+end
+local function get() return value end
+do return end
+function long_function_name(argument_one, argument_two) return argument_one + argument_two end
+local callback = function() return 1 end
+"###), @r###"
+    function M.add(a, b) return a + b end
+    local function get() return value end
+    do return end
+    function long_function_name(argument_one, argument_two)
+    	return argument_one + argument_two
+    end
+    local callback = function() return 1 end
+    "###);
+}
+
+#[test]
+fn test_collapse_anonymous_functions() {
+    insta::assert_snapshot!(format(r###"
+call(function() print("called") end)
+local t = {
+    get = function(self)
+        return self.value
+    end,
+    set = function(self, value) self.value = value end,
+}
+local handler = function(event) return process_event_with_a_long_name(event, first_option, second_option) end
+"###), @r###"
+    call(function() print("called") end)
+    local t = {
+    	get = function(self) return self.value end,
+    	set = function(self, value)
+    		self.value = value
+    	end,
+    }
+    local handler = function(event)
+    	return process_event_with_a_long_name(event, first_option, second_option)
+    end
+    "###);
+}
+
+#[test]
+fn test_comments_expand() {
+    insta::assert_snapshot!(format(r###"
+if x then -- comment
+    return
+end
+if y then
+    -- comment
+    return
+end
+if z then return end -- comment
+"###), @r###"
+    if x then -- comment
+    	return
+    end
+    if y then
+    	-- comment
+    	return
+    end
+    if z then return end -- comment
+    "###);
+}
+
+#[test]
+fn test_disabled_by_default() {
+    assert_eq!(
+        format_code("if not x then return end\n", Config::default(), None).unwrap(),
+        "if not x then\n\treturn\nend\n"
+    );
+}
@@ -0,0 +1,88 @@
+use stylua_lib::{format_code, CommentStyle, Config};
+
+fn format(input: &str, comment_style: CommentStyle) -> String {
+    format_code(
+        input,
+        Config::default()
+            .with_column_width(60)
+            .with_comment_style(comment_style)
+            .with_verify(true),
+        None,
+    )
+    .unwrap()
+}
+
+const INPUT: &str = r###"
+--no space after the dashes
+--    too many spaces after the dashes
+--- A doc comment
+--! a directive
+--------------------
+--local x = call(a, b)
+--if x then
+local z = 2 --trailing comment
+do
+    --This prose comment is long enough that it goes past the column width, so it should be wrapped over several lines
+    print(z)
+end
+--print("this commented-out code is long enough that it goes past the column width")
+--stylua: ignore
+local   y = 1
+"###;
+
+#[test]
+fn test_preserve() {
+    insta::assert_snapshot!(format(INPUT, CommentStyle::Preserve), @r###"
+
+    --no space after the dashes
+    --    too many spaces after the dashes
+    --- A doc comment
+    --! a directive
+    --------------------
+    --local x = call(a, b)
+    --if x then
+    local z = 2 --trailing comment
+    do
+    	--This prose comment is long enough that it goes past the column width, so it should be wrapped over several lines
+    	print(z)
+    end
+    --print("this commented-out code is long enough that it goes past the column width")
+    --stylua: ignore
+    local   y = 1
+    "###);
+}
+
+#[test]
+fn test_normalize() {
+    insta::assert_snapshot!(format(INPUT, CommentStyle::Normalize), @r###"
+
+    -- no space after the dashes
+    -- too many spaces after the dashes
+    --- A doc comment
+    --! a directive
+    --------------------
+    --local x = call(a, b)
+    --if x then
+    local z = 2 -- trailing comment
+    do
+    	-- This prose comment is long enough that it goes past
+    	-- the column width, so it should be wrapped over
+    	-- several lines
+    	print(z)
+    end
+    --print("this commented-out code is long enough that it goes past the column width")
+    --stylua: ignore
+    local   y = 1
+    "###);
+}
+
+#[test]
+fn test_normalize_keeps_internal_whitespace() {
+    insta::assert_snapshot!(format(r###"--Options:  name    the name of the field, which is used  when it is displayed
+--  `a`   and  `b`
+"###, CommentStyle::Normalize), @r###"
+    -- Options:  name    the name of the field, which is used
+    -- when it is displayed
+    -- `a`   and  `b`
+    "###);
+}
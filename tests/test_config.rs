@@ -0,0 +1,207 @@
+mod common;
+
+use common::{assert_success, create_project, read};
+use std::path::Path;
+
+fn run_stylua(cwd: &Path, args: &[&str]) {
+    assert_success(&common::run_stylua(cwd, args));
+}
+
+#[test]
+fn test_nearest_config_file() {
+    let root = create_project(
+        "nearest_config_file",
+        &[
+            ("stylua.toml", "indent_type = \"Spaces\"\n"),
+            ("packages/a/.stylua.toml", "quote_style = \"ForceSingle\"\n"),
+            (
+                "packages/a/src/init.lua",
+                "if x then\nlocal y = \"a\"\nend\n",
+            ),
+            ("packages/b/init.lua", "if x then\nlocal y = \"a\"\nend\n"),
+        ],
+    );
+
+    run_stylua(&root, &["packages"]);
+
+    assert_eq!(
+        read(&root, "packages/a/src/init.lua"),
+        "if x then\n\tlocal y = 'a'\nend\n"
+    );
+    assert_eq!(
+        read(&root, "packages/b/init.lua"),
+        "if x then\n    local y = \"a\"\nend\n"
+    );
+}
+
+#[test]
+fn test_config_root() {
+    let root = create_project(
+        "config_root",
+        &[
+            ("stylua.toml", "indent_type = \"Spaces\"\n"),
+            ("packages/init.lua", "if x then\nlocal y = 1\nend\n"),
+        ],
+    );
+
+    // The configuration above the root is not used
+    run_stylua(&root, &["--config-root", "packages", "packages/init.lua"]);
+
+    assert_eq!(
+        read(&root, "packages/init.lua"),
+        "if x then\n\tlocal y = 1\nend\n"
+    );
+}
+
+#[test]
+fn test_file_outside_root() {
+    let root = create_project(
+        "file_outside_root",
+        &[
+            ("stylua.toml", "indent_type = \"Spaces\"\n"),
+            ("proj/init.lua", "local x = 1\n"),
+            ("other/a.lua", "if x then\nlocal y = 1\nend\n"),
+            ("other/b.lua", "if x then\nlocal y = 1\nend\n"),
+        ],
+    );
+
+    // Files outside of the root do not search above it for a configuration file
+    run_stylua(
+        &root.join("proj"),
+        &["../other/a.lua", "../proj/../other/b.lua"],
+    );
+
+    assert_eq!(
+        read(&root, "other/a.lua"),
+        "if x then\n\tlocal y = 1\nend\n"
+    );
+    assert_eq!(
+        read(&root, "other/b.lua"),
+        "if x then\n\tlocal y = 1\nend\n"
+    );
+}
+
+#[test]
+fn test_overrides() {
+    let root = create_project(
+        "overrides",
+        &[
+            (
+                "stylua.toml",
+                "column_width = 120\n\n[[overrides]]\nfiles = [\"**/*.spec.lua\"]\nquote_style = \"ForceSingle\"\n\n[[overrides]]\nfiles = [\"vendor/**\"]\nindent_type = \"Spaces\"\nindent_width = 2\n",
+            ),
+            ("src/init.lua", "if x then\nlocal y = \"a\"\nend\n"),
+            ("src/init.spec.lua", "if x then\nlocal y = \"a\"\nend\n"),
+            ("vendor/lib.spec.lua", "if x then\nlocal y = \"a\"\nend\n"),
+        ],
+    );
+
+    run_stylua(&root, &["."]);
+
+    assert_eq!(
+        read(&root, "src/init.lua"),
+        "if x then\n\tlocal y = \"a\"\nend\n"
+    );
+    assert_eq!(
+        read(&root, "src/init.spec.lua"),
+        "if x then\n\tlocal y = 'a'\nend\n"
+    );
+    // Later overrides are applied on top of earlier ones
+    assert_eq!(
+        read(&root, "vendor/lib.spec.lua"),
+        "if x then\n  local y = 'a'\nend\n"
+    );
+}
+
+#[test]
+fn test_overrides_unknown_option() {
+    let root = create_project(
+        "overrides_unknown_option",
+        &[
+            (
+                "stylua.toml",
+                "[[overrides]]\nfiles = [\"*.lua\"]\ncolumn_widht = 80\n",
+            ),
+            ("init.lua", "local x = 1\n"),
+        ],
+    );
+
+    let output = common::run_stylua(&root, &["init.lua"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("column_widht"));
+}
+
+#[test]
+fn test_editorconfig() {
+    let root = create_project(
+        "editorconfig",
+        &[
+            (
+                ".editorconfig",
+                "root = true\n\n[*]\nindent_style = space\nindent_size = 2\nunknown_key = value\n\n[*.spec.lua]\nquote_type = single\n\n[vendor/**]\nindent_style = tab\nmax_line_length = 20\n",
+            ),
+            (
+                "src/.editorconfig",
+                "[*.lua]\nindent_size = 4\n",
+            ),
+            ("init.lua", "if x then\nlocal y = \"a\"\nend\n"),
+            ("src/init.spec.lua", "if x then\nlocal y = \"a\"\nend\n"),
+            ("vendor/lib.lua", "local x = {foo = 1, bar = 2}\n"),
+        ],
+    );
+
+    run_stylua(&root, &["."]);
+
+    assert_eq!(
+        read(&root, "init.lua"),
+        "if x then\n  local y = \"a\"\nend\n"
+    );
+    // Sections in nearer files take precedence
+    assert_eq!(
+        read(&root, "src/init.spec.lua"),
+        "if x then\n    local y = 'a'\nend\n"
+    );
+    assert_eq!(
+        read(&root, "vendor/lib.lua"),
+        "local x = {\n\tfoo = 1,\n\tbar = 2,\n}\n"
+    );
+}
+
+#[test]
+fn test_editorconfig_invalid_section() {
+    let root = create_project(
+        "editorconfig_invalid_section",
+        &[
+            (
+                ".editorconfig",
+                "root = true\n\n[*.{lua]\nindent_style = tab\n\n[*.lua]\nindent_style = space\nindent_size = 2\n",
+            ),
+            ("init.lua", "if x then\nlocal y = 1\nend\n"),
+        ],
+    );
+
+    // The invalid section is skipped with a warning, and the rest of the file still applies
+    let output = common::run_stylua(&root, &["init.lua"]);
+    assert_success(&output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("warning: ignoring section"));
+    assert_eq!(read(&root, "init.lua"), "if x then\n  local y = 1\nend\n");
+}
+
+#[test]
+fn test_stylua_toml_takes_precedence_over_editorconfig() {
+    let root = create_project(
+        "stylua_toml_precedence",
+        &[
+            (
+                ".editorconfig",
+                "root = true\n\n[*]\nindent_style = space\n",
+            ),
+            ("stylua.toml", "indent_type = \"Tabs\"\n"),
+            ("init.lua", "if x then\nlocal y = 1\nend\n"),
+        ],
+    );
+
+    run_stylua(&root, &["init.lua"]);
+
+    assert_eq!(read(&root, "init.lua"), "if x then\n\tlocal y = 1\nend\n");
+}
@@ -0,0 +1,39 @@
+use stylua_lib::{format_code, Config, Error};
+
+fn format(input: &str) -> Result<String, Error> {
+    format_code(input, Config::default(), None)
+}
+
+#[test]
+fn test_unexpected_token_error() {
+    match format("local x = 1\nlocal y = = 2\n") {
+        Err(Error::Parse {
+            line,
+            column,
+            byte_range,
+            ..
+        }) => {
+            assert_eq!(line, 2);
+            assert_eq!(column, 11);
+            assert_eq!(byte_range, (22, 23));
+        }
+        other => panic!("expected parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_tokenizer_error() {
+    match format("local x = 1\nlocal y = \"foo\n") {
+        Err(Error::Parse {
+            message,
+            line,
+            byte_range,
+            ..
+        }) => {
+            assert_eq!(message, "unclosed string");
+            assert_eq!(line, 2);
+            assert!(byte_range.0 >= 12);
+        }
+        other => panic!("expected parse error, got {:?}", other),
+    }
+}
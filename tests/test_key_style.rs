@@ -0,0 +1,102 @@
+use stylua_lib::{format_code, Config, KeyStyle};
+
+fn format(input: &str, key_style: KeyStyle) -> String {
+    format_code(
+        input,
+        Config::default()
+            .with_key_style(key_style)
+            .with_verify(true),
+        None,
+    )
+    .unwrap()
+}
+
+const INPUT: &str = r###"
+local mixed = {
+    ["name"] = 1,
+    ["end"] = 2,
+    other = 3,
+    [1] = 4,
+    ["with space"] = 5,
+}
+local names = { ["a"] = 1, b = 2, ["_c1"] = 3 }
+print(t["name"], t["end"], t["with space"], t[1], t["a"]["b"])
+local commented = t[--[[ comment ]] "name"]
+"###;
+
+#[test]
+fn test_preserve() {
+    insta::assert_snapshot!(format(INPUT, KeyStyle::Preserve), @r###"
+    local mixed = {
+    	["name"] = 1,
+    	["end"] = 2,
+    	other = 3,
+    	[1] = 4,
+    	["with space"] = 5,
+    }
+    local names = { ["a"] = 1, b = 2, ["_c1"] = 3 }
+    print(t["name"], t["end"], t["with space"], t[1], t["a"]["b"])
+    local commented = t[--[[ comment ]]"name"]
+    "###);
+}
+
+#[test]
+fn test_identifier() {
+    insta::assert_snapshot!(format(INPUT, KeyStyle::Identifier), @r###"
+    local mixed = {
+    	name = 1,
+    	["end"] = 2,
+    	other = 3,
+    	[1] = 4,
+    	["with space"] = 5,
+    }
+    local names = { a = 1, b = 2, _c1 = 3 }
+    print(t.name, t["end"], t["with space"], t[1], t.a.b)
+    local commented = t[--[[ comment ]]"name"]
+    "###);
+}
+
+#[test]
+fn test_consistent() {
+    insta::assert_snapshot!(format(INPUT, KeyStyle::Consistent), @r###"
+    local mixed = {
+    	["name"] = 1,
+    	["end"] = 2,
+    	["other"] = 3,
+    	[1] = 4,
+    	["with space"] = 5,
+    }
+    local names = { a = 1, b = 2, _c1 = 3 }
+    print(t.name, t["end"], t["with space"], t[1], t.a.b)
+    local commented = t[--[[ comment ]]"name"]
+    "###);
+}
+
+#[test]
+fn test_goto_is_reserved() {
+    let input = "local t = { [\"goto\"] = 1 }\nprint(t[\"goto\"])\n";
+    for key_style in [KeyStyle::Identifier, KeyStyle::Consistent] {
+        assert_eq!(format(input, key_style), input);
+    }
+}
+
+#[test]
+fn test_rewritten_keys_are_measured() {
+    // Adding brackets makes the first table too long for a single line, and removing them lets the second fit
+    insta::assert_snapshot!(format(r###"
+local t = { alpha = 1, beta = 2, gamma = 3, delta = 4, epsilon = 5, zeta = 6, eta = 7, ["with space"] = 8, }
+local u = { ["alpha"] = 1, ["beta"] = 2, ["gamma"] = 3, ["delta"] = 4, ["epsilon"] = 5, ["zeta"] = 6, ["eta"] = 7 }
+"###, KeyStyle::Consistent), @r###"
+    local t = {
+    	["alpha"] = 1,
+    	["beta"] = 2,
+    	["gamma"] = 3,
+    	["delta"] = 4,
+    	["epsilon"] = 5,
+    	["zeta"] = 6,
+    	["eta"] = 7,
+    	["with space"] = 8,
+    }
+    local u = { alpha = 1, beta = 2, gamma = 3, delta = 4, epsilon = 5, zeta = 6, eta = 7 }
+    "###);
+}
@@ -0,0 +1,238 @@
+mod common;
+
+use common::create_project;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_stylua"))
+            .arg("--lsp")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        Client {
+            child,
+            stdin,
+            stdout,
+        }
+    }
+
+    fn send(&mut self, message: Value) {
+        let content = message.to_string();
+        write!(
+            self.stdin,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        )
+        .unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            self.stdout.read_line(&mut header).unwrap();
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(length) = header.strip_prefix("Content-Length:") {
+                content_length = length.trim().parse().unwrap();
+            }
+        }
+
+        let mut content = vec![0; content_length];
+        self.stdout.read_exact(&mut content).unwrap();
+        serde_json::from_slice(&content).unwrap()
+    }
+
+    fn request(&mut self, id: u64, method: &str, params: Value) -> Value {
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        let response = self.receive();
+        assert_eq!(response["id"], id);
+        response
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    fn open(&mut self, uri: &str, text: &str) -> Value {
+        self.notify(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": uri, "languageId": "lua", "version": 1, "text": text } }),
+        );
+        self.receive()
+    }
+
+    fn exit(mut self) {
+        self.request(1000, "shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+        assert!(self.child.wait().unwrap().success());
+    }
+}
+
+#[test]
+fn test_formatting() {
+    let mut client = Client::start();
+    let response = client.request(1, "initialize", json!({ "capabilities": {} }));
+    assert_eq!(
+        response["result"]["capabilities"]["documentFormattingProvider"],
+        true
+    );
+    client.notify("initialized", json!({}));
+
+    let diagnostics = client.open("file:///test.lua", "local  x   =  1\nlocal y = 2\n");
+    assert_eq!(diagnostics["params"]["diagnostics"], json!([]));
+
+    let response = client.request(
+        2,
+        "textDocument/formatting",
+        json!({ "textDocument": { "uri": "file:///test.lua" }, "options": { "tabSize": 4, "insertSpaces": false } }),
+    );
+    assert_eq!(
+        response["result"],
+        json!([{
+            "range": {
+                "start": { "line": 0, "character": 6 },
+                "end": { "line": 0, "character": 13 },
+            },
+            "newText": "x =",
+        }])
+    );
+
+    client.exit();
+}
+
+#[test]
+fn test_percent_encoded_uri() {
+    let root = create_project(
+        "lsp project",
+        &[("stylua.toml", "quote_style = \"ForceSingle\"\n")],
+    );
+    let uri = format!(
+        "file://{}/a.lua",
+        root.display().to_string().replace(' ', "%20")
+    );
+
+    let mut client = Client::start();
+    client.request(1, "initialize", json!({ "capabilities": {} }));
+
+    // The configuration file is found in the decoded directory
+    let diagnostics = client.open(&uri, "local x = \"a\"\n");
+    assert_eq!(diagnostics["params"]["diagnostics"], json!([]));
+    let response = client.request(
+        2,
+        "textDocument/formatting",
+        json!({ "textDocument": { "uri": uri }, "options": { "tabSize": 4, "insertSpaces": false } }),
+    );
+    assert_eq!(
+        response["result"],
+        json!([{
+            "range": {
+                "start": { "line": 0, "character": 10 },
+                "end": { "line": 0, "character": 13 },
+            },
+            "newText": "'a'",
+        }])
+    );
+
+    client.exit();
+}
+
+#[test]
+fn test_range_formatting() {
+    let mut client = Client::start();
+    client.request(1, "initialize", json!({ "capabilities": {} }));
+    client.open("file:///test.lua", "local  x = 1\nlocal  y = 2\n");
+
+    let response = client.request(
+        2,
+        "textDocument/rangeFormatting",
+        json!({
+            "textDocument": { "uri": "file:///test.lua" },
+            "range": { "start": { "line": 1, "character": 0 }, "end": { "line": 2, "character": 0 } },
+            "options": { "tabSize": 4, "insertSpaces": false },
+        }),
+    );
+    assert_eq!(
+        response["result"],
+        json!([{
+            "range": {
+                "start": { "line": 1, "character": 6 },
+                "end": { "line": 1, "character": 7 },
+            },
+            "newText": "",
+        }])
+    );
+
+    client.exit();
+}
+
+#[test]
+fn test_range_formatting_invalid_position() {
+    let mut client = Client::start();
+    client.request(1, "initialize", json!({ "capabilities": {} }));
+    client.open("file:///test.lua", "local  x = 1\nlocal  y = 2\n");
+
+    // A range ending past the end of the document is rejected, rather than formatting to the end
+    let response = client.request(
+        2,
+        "textDocument/rangeFormatting",
+        json!({
+            "textDocument": { "uri": "file:///test.lua" },
+            "range": { "start": { "line": 1, "character": 0 }, "end": { "line": 5, "character": 0 } },
+            "options": { "tabSize": 4, "insertSpaces": false },
+        }),
+    );
+    assert_eq!(response["error"]["code"], json!(-32602));
+    assert_eq!(response["result"], Value::Null);
+
+    client.exit();
+}
+
+#[test]
+fn test_parse_error_diagnostics() {
+    let mut client = Client::start();
+    client.request(1, "initialize", json!({ "capabilities": {} }));
+
+    let diagnostics = client.open("file:///test.lua", "local x = = 1\n");
+    let diagnostic = &diagnostics["params"]["diagnostics"][0];
+    assert_eq!(
+        diagnostic["range"],
+        json!({ "start": { "line": 0, "character": 10 }, "end": { "line": 0, "character": 11 } })
+    );
+
+    // Formatting a document which cannot be parsed makes no changes
+    let response = client.request(
+        2,
+        "textDocument/formatting",
+        json!({ "textDocument": { "uri": "file:///test.lua" }, "options": { "tabSize": 4, "insertSpaces": false } }),
+    );
+    assert_eq!(response["result"], Value::Null);
+
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": "file:///test.lua", "version": 2 },
+            "contentChanges": [{ "text": "local x = 1\n" }],
+        }),
+    );
+    assert_eq!(client.receive()["params"]["diagnostics"], json!([]));
+
+    client.exit();
+}
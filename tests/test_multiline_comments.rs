@@ -0,0 +1,16 @@
+use stylua_lib::{format_code, Config};
+
+#[test]
+fn test_format_multiline_comments_disabled() {
+    let input =
+        "if x then\n        --[[   \n        comment\n        ]]\n        local y = 1\nend\n";
+    assert_eq!(
+        format_code(
+            input,
+            Config::default().with_format_multiline_comments(false),
+            None
+        )
+        .unwrap(),
+        "if x then\n\t--[[   \n        comment\n        ]]\n\tlocal y = 1\nend\n"
+    );
+}
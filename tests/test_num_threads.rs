@@ -0,0 +1,66 @@
+mod common;
+
+use common::{create_project, run_stylua};
+use std::fs;
+
+fn project_files() -> Vec<(String, String)> {
+    let mut files: Vec<(String, String)> = (0..50)
+        .map(|index| {
+            (
+                format!("dir{}/file{}.lua", index % 5, index),
+                format!("local   x{} = {{1,2}}\n", index),
+            )
+        })
+        .collect();
+    files.push((
+        String::from("dir2/invalid.lua"),
+        String::from("local = 1\n"),
+    ));
+    files.push((String::from("formatted.lua"), String::from("local x = 1\n")));
+    files
+}
+
+#[test]
+fn test_check_output_is_deterministic() {
+    let root = create_project("num_threads_check", &project_files());
+
+    let sequential = run_stylua(&root, &["--check", "--num-threads", "1", "."]);
+    let parallel = run_stylua(&root, &["--check", "--num-threads", "8", "."]);
+
+    assert_eq!(sequential.status.code(), Some(1));
+    assert_eq!(parallel.status.code(), Some(1));
+    assert_eq!(sequential.stdout, parallel.stdout);
+    assert_eq!(sequential.stderr, parallel.stderr);
+
+    // Diffs are reported in path order
+    let stdout = String::from_utf8_lossy(&parallel.stdout);
+    let diff_paths: Vec<&str> = stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("Diff in "))
+        .collect();
+    let mut sorted_paths = diff_paths.clone();
+    sorted_paths.sort_unstable();
+    assert_eq!(diff_paths.len(), 50);
+    assert_eq!(diff_paths, sorted_paths);
+    assert!(String::from_utf8_lossy(&parallel.stderr).contains("invalid.lua"));
+}
+
+#[test]
+fn test_format_in_parallel() {
+    let root = create_project("num_threads_format", &project_files());
+
+    let output = run_stylua(&root, &["--num-threads", "4", "."]);
+    assert_eq!(output.status.code(), Some(1));
+
+    for index in 0..50 {
+        let path = root.join(format!("dir{}/file{}.lua", index % 5, index));
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            format!("local x{} = {{ 1, 2 }}\n", index)
+        );
+    }
+    assert_eq!(
+        fs::read_to_string(root.join("dir2/invalid.lua")).unwrap(),
+        "local = 1\n"
+    );
+}
@@ -0,0 +1,60 @@
+use stylua_lib::{format_code, Config, NumberCase};
+
+fn format(config: Config) -> String {
+    let contents = std::fs::read_to_string("tests/inputs/number.lua").unwrap();
+    format_code(&contents, config.with_verify(true), None).unwrap()
+}
+
+#[test]
+fn test_lowercase() {
+    insta::assert_snapshot!(
+        "lowercase",
+        format(
+            Config::default()
+                .with_hex_prefix_case(NumberCase::Lower)
+                .with_hex_digit_case(NumberCase::Lower)
+                .with_exponent_case(NumberCase::Lower)
+        )
+    );
+}
+
+#[test]
+fn test_uppercase() {
+    insta::assert_snapshot!(
+        "uppercase",
+        format(
+            Config::default()
+                .with_hex_prefix_case(NumberCase::Upper)
+                .with_hex_digit_case(NumberCase::Upper)
+                .with_exponent_case(NumberCase::Upper)
+        )
+    );
+}
+
+#[test]
+fn test_lowercase_prefix_uppercase_digits() {
+    insta::assert_snapshot!(
+        "lowercase_prefix_uppercase_digits",
+        format(
+            Config::default()
+                .with_hex_prefix_case(NumberCase::Lower)
+                .with_hex_digit_case(NumberCase::Upper)
+        )
+    );
+}
+
+#[test]
+fn test_remove_exponent_plus() {
+    insta::assert_snapshot!(
+        "remove_exponent_plus",
+        format(Config::default().with_remove_exponent_plus(true))
+    );
+}
+
+#[test]
+fn test_normalize_trailing_dot() {
+    insta::assert_snapshot!(
+        "normalize_trailing_dot",
+        format(Config::default().with_normalize_trailing_dot(true))
+    );
+}
@@ -0,0 +1,234 @@
+mod common;
+
+use common::{create_project, run_stylua, run_stylua_with_stdin, stylua};
+use std::fs;
+use std::process::Command;
+
+/// Runs StyLua in check mode with the given output format, returning its output and exit code
+fn check(name: &str, args: &[&str]) -> (String, i32) {
+    let root = create_project(
+        name,
+        &[
+            ("a.lua", "local   x = 1\nlocal y = 2\nlocal z = 3\n"),
+            ("b.lua", "local a = {1,2}\n"),
+            ("formatted.lua", "local b = 1\n"),
+        ],
+    );
+
+    let args = [&["--check", "a.lua", "b.lua", "formatted.lua"], args].concat();
+    let output = run_stylua(&root, &args);
+
+    (
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        output.status.code().unwrap(),
+    )
+}
+
+/// Runs StyLua in check mode on the given code read from stdin, returning its output and exit code
+fn check_stdin(name: &str, args: &[&str], input: &str) -> (String, i32) {
+    let root = create_project::<&str, &str>(name, &[]);
+
+    let args = [&["--check"], args, &["-"]].concat();
+    let output = run_stylua_with_stdin(&root, &args, input);
+
+    (
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        output.status.code().unwrap(),
+    )
+}
+
+#[test]
+fn test_json() {
+    let (output, code) = check("output_format_json", &["--output-format", "json"]);
+    assert_eq!(code, 1);
+
+    let files: serde_json::Value = serde_json::from_str(&output).unwrap();
+    insta::assert_snapshot!(serde_json::to_string_pretty(&files).unwrap(), @r###"
+    [
+      {
+        "file": "a.lua",
+        "hunks": [
+          {
+            "diff": "-local   x = 1\n+local x = 1\n local y = 2\n local z = 3\n",
+            "formatted_lines": 3,
+            "formatted_start": 1,
+            "original_lines": 3,
+            "original_start": 1
+          }
+        ]
+      },
+      {
+        "file": "b.lua",
+        "hunks": [
+          {
+            "diff": "-local a = {1,2}\n+local a = { 1, 2 }\n",
+            "formatted_lines": 1,
+            "formatted_start": 1,
+            "original_lines": 1,
+            "original_start": 1
+          }
+        ]
+      }
+    ]
+    "###);
+}
+
+#[test]
+fn test_checkstyle() {
+    let (output, code) = check(
+        "output_format_checkstyle",
+        &["--output-format", "checkstyle"],
+    );
+    assert_eq!(code, 1);
+    insta::assert_snapshot!(output, @r###"
+    <?xml version="1.0" encoding="utf-8"?>
+    <checkstyle version="4.3">
+      <file name="a.lua">
+        <error line="1" severity="warning" message="Code should be formatted as:&#10;local x = 1" source="stylua" />
+      </file>
+      <file name="b.lua">
+        <error line="1" severity="warning" message="Code should be formatted as:&#10;local a = { 1, 2 }" source="stylua" />
+      </file>
+    </checkstyle>
+    "###);
+}
+
+#[test]
+fn test_github() {
+    let (output, code) = check("output_format_github", &["--output-format", "github"]);
+    assert_eq!(code, 1);
+    insta::assert_snapshot!(output, @r###"
+    ::warning file=a.lua,line=1,endLine=1,title=StyLua::Code should be formatted as:%0Alocal x = 1
+    ::warning file=b.lua,line=1,endLine=1,title=StyLua::Code should be formatted as:%0Alocal a = { 1, 2 }
+    "###);
+}
+
+#[test]
+fn test_unified() {
+    let (output, code) = check(
+        "output_format_unified",
+        &["--output-format", "unified", "--diff-context", "1"],
+    );
+    assert_eq!(code, 1);
+    insta::assert_snapshot!(output, @r###"
+    --- a/a.lua
+    +++ b/a.lua
+    @@ -1,2 +1,2 @@
+    -local   x = 1
+    +local x = 1
+     local y = 2
+    --- a/b.lua
+    +++ b/b.lua
+    @@ -1,1 +1,1 @@
+    -local a = {1,2}
+    +local a = { 1, 2 }
+    "###);
+}
+
+#[test]
+fn test_unified_git_apply() {
+    let root = create_project(
+        "output_format_unified_git_apply",
+        &[
+            ("a.lua", "local   x = 1\n"),
+            ("src/b.lua", "local a = {1,2}\n"),
+        ],
+    );
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .current_dir(&root)
+            .output()
+            .unwrap()
+    };
+    assert!(git(&["init", "-q"]).status.success());
+
+    // Paths are written relative to the working directory, however the files were given
+    let absolute_path = root.join("src/b.lua").display().to_string();
+    for (files, expected_headers) in [
+        (
+            vec!["."],
+            vec![
+                "--- a/a.lua",
+                "+++ b/a.lua",
+                "--- a/src/b.lua",
+                "+++ b/src/b.lua",
+            ],
+        ),
+        (
+            vec![absolute_path.as_str()],
+            vec!["--- a/src/b.lua", "+++ b/src/b.lua"],
+        ),
+    ] {
+        let output = stylua(&root)
+            .args(["--check", "--output-format", "unified"])
+            .args(&files)
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1));
+
+        let patch = String::from_utf8_lossy(&output.stdout);
+        let headers: Vec<_> = patch
+            .lines()
+            .filter(|line| line.starts_with("---") || line.starts_with("+++"))
+            .collect();
+        assert_eq!(headers, expected_headers);
+
+        fs::write(root.join("stylua.patch"), patch.as_bytes()).unwrap();
+        let apply = git(&["apply", "--check", "stylua.patch"]);
+        assert!(
+            apply.status.success(),
+            "{}",
+            String::from_utf8_lossy(&apply.stderr)
+        );
+    }
+}
+
+#[test]
+fn test_no_differences() {
+    let root = create_project(
+        "output_format_no_differences",
+        &[("a.lua", "local x = 1\n")],
+    );
+
+    let output = run_stylua(&root, &["--check", "--output-format", "json", "a.lua"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "[]\n");
+}
+
+#[test]
+fn test_check_stdin() {
+    let (output, code) = check_stdin("check_stdin", &["--color", "never"], "local   x = 1\n");
+    assert_eq!(code, 1);
+    insta::assert_snapshot!(output, @r###"
+    Diff in stdin:
+    1        |-local   x = 1
+        1    |+local x = 1
+    "###);
+
+    let (output, code) = check_stdin("check_stdin", &[], "local x = 1\n");
+    assert_eq!(code, 0);
+    assert_eq!(output, "");
+}
+
+#[test]
+fn test_check_stdin_name() {
+    let (output, code) = check_stdin(
+        "check_stdin_name",
+        &[
+            "--stdin-filepath",
+            "src/init.lua",
+            "--output-format",
+            "unified",
+        ],
+        "local a = {1,2}\n",
+    );
+    assert_eq!(code, 1);
+    insta::assert_snapshot!(output, @r###"
+    --- a/src/init.lua
+    +++ b/src/init.lua
+    @@ -1,1 +1,1 @@
+    -local a = {1,2}
+    +local a = { 1, 2 }
+    "###);
+}
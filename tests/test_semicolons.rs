@@ -0,0 +1,69 @@
+use stylua_lib::{format_code, Config, SemicolonStyle};
+
+fn format(input: &str, semicolons: SemicolonStyle) -> String {
+    format_code(
+        input,
+        Config::default()
+            .with_semicolons(semicolons)
+            .with_verify(true),
+        None,
+    )
+    .unwrap()
+}
+
+const INPUT: &str = r###"
+local a = 1;
+local b = 2 -- comment
+print(a); -- after semicolon
+local c = a;
+(f or g)()
+do
+    print(b) -- trailing
+    return b;
+end
+"###;
+
+#[test]
+fn test_remove() {
+    insta::assert_snapshot!(format(INPUT, SemicolonStyle::Remove), @r###"
+    local a = 1
+    local b = 2 -- comment
+    print(a) -- after semicolon
+    local c = a;
+    (f or g)()
+    do
+    	print(b) -- trailing
+    	return b
+    end
+    "###);
+}
+
+#[test]
+fn test_preserve() {
+    insta::assert_snapshot!(format(INPUT, SemicolonStyle::Preserve), @r###"
+    local a = 1;
+    local b = 2 -- comment
+    print(a); -- after semicolon
+    local c = a;
+    (f or g)()
+    do
+    	print(b) -- trailing
+    	return b;
+    end
+    "###);
+}
+
+#[test]
+fn test_always() {
+    insta::assert_snapshot!(format(INPUT, SemicolonStyle::Always), @r###"
+    local a = 1;
+    local b = 2; -- comment
+    print(a); -- after semicolon
+    local c = a;
+    (f or g)();
+    do
+    	print(b); -- trailing
+    	return b;
+    end;
+    "###);
+}
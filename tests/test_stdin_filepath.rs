@@ -0,0 +1,130 @@
+mod common;
+
+use common::{assert_success, create_project, run_stylua_with_stdin};
+use std::path::Path;
+
+fn format_stdin(cwd: &Path, args: &[&str], input: &str) -> String {
+    let output = run_stylua_with_stdin(cwd, args, input);
+    assert_success(&output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_stdin_filepath_config() {
+    let root = create_project(
+        "stdin_filepath_config",
+        &[
+            ("stylua.toml", "indent_type = \"Spaces\"\n"),
+            ("packages/a/stylua.toml", "quote_style = \"ForceSingle\"\n"),
+        ],
+    );
+    let input = "local x = \"a\"\n";
+
+    assert_eq!(format_stdin(&root, &["-"], input), "local x = \"a\"\n");
+    assert_eq!(
+        format_stdin(
+            &root,
+            &["--stdin-filepath", "packages/a/src/init.lua", "-"],
+            input
+        ),
+        "local x = 'a'\n"
+    );
+    assert_eq!(
+        format_stdin(
+            &root,
+            &["--stdin-filepath", "packages/b/init.lua", "-"],
+            input
+        ),
+        "local x = \"a\"\n"
+    );
+}
+
+#[test]
+fn test_stdin_filepath_ignored() {
+    let root = create_project(
+        "stdin_filepath_ignored",
+        &[
+            (".styluaignore", "generated/\n"),
+            ("src/.styluaignore", "*.spec.lua\n!keep.spec.lua\n"),
+        ],
+    );
+    let input = "local   x=1\n";
+
+    for path in [
+        "generated/init.lua",
+        "src/generated/nested/init.lua",
+        "src/init.spec.lua",
+        "src/../generated/init.lua",
+    ] {
+        assert_eq!(
+            format_stdin(&root, &["--stdin-filepath", path, "-"], input),
+            input,
+            "{}",
+            path
+        );
+    }
+
+    for path in ["init.lua", "src/init.lua", "src/keep.spec.lua"] {
+        assert_eq!(
+            format_stdin(&root, &["--stdin-filepath", path, "-"], input),
+            "local x = 1\n",
+            "{}",
+            path
+        );
+    }
+
+    // Ignored code is output unchanged, even if it cannot be parsed
+    assert_eq!(
+        format_stdin(
+            &root,
+            &["--stdin-filepath", "generated/init.lua", "-"],
+            "local = 1\n"
+        ),
+        "local = 1\n"
+    );
+
+    // Ignored code always passes the check, without outputting anything
+    assert_eq!(
+        format_stdin(
+            &root,
+            &["--check", "--stdin-filepath", "generated/init.lua", "-"],
+            input
+        ),
+        ""
+    );
+}
+
+#[test]
+fn test_stdin_filepath_glob() {
+    let root = create_project::<&str, &str>("stdin_filepath_glob", &[]);
+    let input = "local   x=1\n";
+
+    // Code for a path excluded by the glob patterns given is output unchanged, as when walking files.
+    // `-` comes first, as `--glob` takes multiple values
+    for path in ["init.spec.lua", "src/init.spec.lua", "init.luau"] {
+        let args = [
+            "-",
+            "--stdin-filepath",
+            path,
+            "--glob",
+            "**/*.lua",
+            "!**/*.spec.lua",
+        ];
+        assert_eq!(format_stdin(&root, &args, input), input, "{}", path);
+    }
+    let args = [
+        "-",
+        "--stdin-filepath",
+        "src/init.lua",
+        "--glob",
+        "**/*.lua",
+        "!**/*.spec.lua",
+    ];
+    assert_eq!(format_stdin(&root, &args, input), "local x = 1\n");
+
+    // Without a path, the glob patterns do not apply
+    assert_eq!(
+        format_stdin(&root, &["-", "--glob", "!**/*.lua"], input),
+        "local x = 1\n"
+    );
+}
@@ -0,0 +1,26 @@
+mod common;
+
+use common::{create_project, run_stylua};
+
+#[test]
+fn test_default_glob() {
+    let root = create_project(
+        "syntax_default_glob",
+        &[
+            ("a.lua", "local x = 1\n"),
+            ("b.luau", "local   x : number = 1\n"),
+        ],
+    );
+
+    let output = run_stylua(&root, &["--check", "."]);
+
+    // `.luau` files are only searched for when they can be parsed
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if cfg!(feature = "luau") {
+        assert_eq!(output.status.code(), Some(1));
+        assert!(stdout.contains("b.luau"));
+    } else {
+        assert_eq!(output.status.code(), Some(0));
+        assert_eq!(stdout, "");
+    }
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use common::{create_project, read, run_stylua};
+use stylua_lib::{format_code, Config};
+
+fn format(input: &str) -> String {
+    format_code(input, Config::default().with_verify(true), None).unwrap()
+}
+
+#[test]
+#[cfg_attr(feature = "luau", ignore)]
+fn test_verify_standard() {
+    insta::glob!("inputs/*.lua", |path| {
+        let contents = std::fs::read_to_string(path).unwrap();
+        format(&contents);
+    })
+}
+
+#[test]
+#[cfg(feature = "luau")]
+fn test_verify_luau() {
+    insta::glob!("inputs-luau/*.lua", |path| {
+        let contents = std::fs::read_to_string(path).unwrap();
+        format(&contents);
+    })
+}
+
+#[test]
+fn test_verify_failure() {
+    // The formatter joins the unary operators in `- - y` into `--y`, which starts a comment, so the output cannot be parsed
+    let root = create_project("verify_failure", &[("a.lua", "local x = - - y\n")]);
+
+    let output = run_stylua(&root, &["--verify", "a.lua"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("verification failed"));
+    assert_eq!(read(&root, "a.lua"), "local x = - - y\n");
+}